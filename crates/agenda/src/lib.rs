@@ -0,0 +1,149 @@
+// Events of local iCalendar (.ics) exports with recurrences expanded. Files
+// are parsed on first use and again whenever they are modified.
+mod parse;
+mod rrule;
+
+use chrono::{DateTime, Duration, Local, NaiveDate};
+use ical::IcalParser;
+use log::{info, warn};
+use once_cell::sync::{Lazy, OnceCell};
+use parse::VEvent;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Event {
+    pub summary: String,
+    pub location: Option<String>,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub all_day: bool,
+    /// file stem of the .ics the event comes from
+    pub calendar: String,
+}
+
+struct Calendar {
+    modified: Option<SystemTime>,
+    events: Arc<Vec<VEvent>>,
+}
+
+static PATHS: OnceCell<Vec<PathBuf>> = OnceCell::new();
+static CALENDARS: Lazy<Mutex<HashMap<PathBuf, Calendar>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn init(paths: Vec<String>) {
+    PATHS.get_or_init(|| {
+        for path in paths.iter() {
+            info!("calendar: {}", path);
+        }
+        paths.into_iter().map(PathBuf::from).collect()
+    });
+}
+
+fn load(path: &Path) -> Result<Vec<VEvent>, String> {
+    let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    read(BufReader::new(file))
+}
+
+fn read<B: BufRead>(reader: B) -> Result<Vec<VEvent>, String> {
+    let mut events = Vec::new();
+    for calendar in IcalParser::new(reader) {
+        let calendar = calendar.map_err(|e| e.to_string())?;
+        events.extend(calendar.events.iter().filter_map(VEvent::parse));
+    }
+    Ok(events)
+}
+
+fn calendar(path: &Path) -> Arc<Vec<VEvent>> {
+    let modified = std::fs::metadata(path).and_then(|x| x.modified()).ok();
+    let mut calendars = CALENDARS.lock().unwrap();
+    if let Some(calendar) = calendars.get(path) {
+        if calendar.modified == modified {
+            return calendar.events.clone();
+        }
+    }
+    let events = Arc::new(load(path).unwrap_or_else(|e| {
+        warn!("failed to load {}: {}", path.display(), e);
+        Vec::new()
+    }));
+    info!("{} events in {}", events.len(), path.display());
+    calendars.insert(
+        path.to_path_buf(),
+        Calendar {
+            modified,
+            events: events.clone(),
+        },
+    );
+    events
+}
+
+/// occurrences overlapping `[from, to)` of all calendars, by start time
+pub fn events(from: DateTime<Local>, to: DateTime<Local>) -> Vec<Event> {
+    let mut result = Vec::new();
+    for path in PATHS.get().map(|x| x.as_slice()).unwrap_or_default() {
+        let name = path
+            .file_stem()
+            .map(|x| x.to_string_lossy().to_string())
+            .unwrap_or_default();
+        result.extend(expand(&calendar(path), &name, from, to));
+    }
+    result.sort_by(|a, b| (a.start, &a.summary).cmp(&(b.start, &b.summary)));
+    result
+}
+
+/// occurrences overlapping `[from, to)` of the events of one calendar
+fn expand(events: &[VEvent], name: &str, from: DateTime<Local>, to: DateTime<Local>) -> Vec<Event> {
+    let mut result = Vec::new();
+    // instances edited on their own replace the generated ones
+    let overridden: HashSet<(&str, DateTime<Local>)> = events
+        .iter()
+        .filter_map(|x| Some((x.uid.as_deref()?, x.recurrence_id?)))
+        .collect();
+    for event in events.iter() {
+        let starts: Vec<DateTime<Local>> = match (&event.rule, event.recurrence_id) {
+            (Some(rule), None) => rule
+                .occurrences(event.start.naive, event.start.zone.localize(to))
+                .into_iter()
+                .filter_map(|x| event.start.zone.resolve(x))
+                .filter(|x| !event.exdates.contains(x))
+                .filter(|x| {
+                    event
+                        .uid
+                        .as_deref()
+                        .is_none_or(|uid| !overridden.contains(&(uid, *x)))
+                })
+                .collect(),
+            _ => event.start.resolve().into_iter().collect(),
+        };
+        for start in starts {
+            let end = start + event.duration;
+            if start < to && (end > from || (end == start && start >= from)) {
+                result.push(Event {
+                    summary: event.summary.clone(),
+                    location: event.location.clone(),
+                    start,
+                    end,
+                    all_day: event.start.all_day,
+                    calendar: name.to_string(),
+                });
+            }
+        }
+    }
+    result
+}
+
+/// events of `days` days starting at `date`
+pub fn agenda(date: NaiveDate, days: i64) -> Vec<Event> {
+    let midnight = |date: NaiveDate| {
+        date.and_hms_opt(0, 0, 0)
+            .and_then(|x| x.and_local_timezone(Local).earliest())
+    };
+    match (midnight(date), midnight(date + Duration::days(days))) {
+        (Some(from), Some(to)) => events(from, to),
+        _ => Vec::new(),
+    }
+}
@@ -0,0 +1,202 @@
+use super::rrule::Rule;
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use ical::parser::ical::component::IcalEvent;
+use ical::property::Property;
+
+/// how a DTSTART-like value is anchored
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Zone {
+    Utc,
+    Named(Tz),
+    /// floating times and dates, unknown TZIDs fall back here too
+    Local,
+}
+
+impl Zone {
+    pub fn resolve(&self, naive: NaiveDateTime) -> Option<DateTime<Local>> {
+        match self {
+            Zone::Utc => Some(Utc.from_utc_datetime(&naive).with_timezone(&Local)),
+            Zone::Named(tz) => tz
+                .from_local_datetime(&naive)
+                .earliest()
+                .map(|x| x.with_timezone(&Local)),
+            Zone::Local => Local.from_local_datetime(&naive).earliest(),
+        }
+    }
+
+    /// the wall clock time of `time` in this zone
+    pub fn localize(&self, time: DateTime<Local>) -> NaiveDateTime {
+        match self {
+            Zone::Utc => time.naive_utc(),
+            Zone::Named(tz) => time.with_timezone(tz).naive_local(),
+            Zone::Local => time.naive_local(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Stamp {
+    pub naive: NaiveDateTime,
+    pub zone: Zone,
+    pub all_day: bool,
+}
+
+impl Stamp {
+    pub fn resolve(&self) -> Option<DateTime<Local>> {
+        self.zone.resolve(self.naive)
+    }
+}
+
+/// a VEVENT before recurrences are expanded
+#[derive(Debug, Clone)]
+pub struct VEvent {
+    pub uid: Option<String>,
+    pub summary: String,
+    pub location: Option<String>,
+    pub start: Stamp,
+    pub duration: Duration,
+    pub rule: Option<Rule>,
+    pub exdates: Vec<DateTime<Local>>,
+    /// set on an edited instance of a recurring event
+    pub recurrence_id: Option<DateTime<Local>>,
+}
+
+fn param<'a>(property: &'a Property, name: &str) -> Option<&'a str> {
+    property
+        .params
+        .as_ref()?
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .and_then(|(_, values)| values.first())
+        .map(|x| x.as_str())
+}
+
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => result.push('\n'),
+            Some(c) => result.push(c),
+            None => {}
+        }
+    }
+    result
+}
+
+pub fn parse_stamp(value: &str, tzid: Option<&str>) -> Option<Stamp> {
+    let value = value.trim();
+    if value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return Some(Stamp {
+            naive: date.and_hms_opt(0, 0, 0)?,
+            zone: Zone::Local,
+            all_day: true,
+        });
+    }
+    let (value, utc) = match value.strip_suffix('Z') {
+        Some(value) => (value, true),
+        None => (value, false),
+    };
+    let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    let zone = if utc {
+        Zone::Utc
+    } else {
+        match tzid.and_then(|x| x.parse::<Tz>().ok()) {
+            Some(tz) => Zone::Named(tz),
+            None => Zone::Local,
+        }
+    };
+    Some(Stamp {
+        naive,
+        zone,
+        all_day: false,
+    })
+}
+
+fn stamp(property: &Property) -> Option<Stamp> {
+    parse_stamp(property.value.as_deref()?, param(property, "TZID"))
+}
+
+/// P1W, P1D, PT1H30M, -PT15M
+fn parse_duration(value: &str) -> Option<Duration> {
+    let (sign, value) = match value.strip_prefix('-') {
+        Some(value) => (-1, value),
+        None => (1, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let value = value.strip_prefix('P')?;
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {}
+            unit => {
+                let n: i64 = number.parse().ok()?;
+                number.clear();
+                total += match unit {
+                    'W' => Duration::weeks(n),
+                    'D' => Duration::days(n),
+                    'H' => Duration::hours(n),
+                    'M' => Duration::minutes(n),
+                    'S' => Duration::seconds(n),
+                    _ => return None,
+                };
+            }
+        }
+    }
+    Some(total * sign)
+}
+
+impl VEvent {
+    pub fn parse(event: &IcalEvent) -> Option<VEvent> {
+        let find = |name: &str| event.properties.iter().find(|x| x.name == name);
+        let start = stamp(find("DTSTART")?)?;
+        let duration = match (find("DTEND").and_then(stamp), find("DURATION")) {
+            (Some(end), _) => end.resolve()? - start.resolve()?,
+            (None, Some(duration)) => parse_duration(duration.value.as_deref()?)?,
+            (None, None) if start.all_day => Duration::days(1),
+            (None, None) => Duration::zero(),
+        };
+        let rule = find("RRULE")
+            .and_then(|x| x.value.as_deref())
+            .and_then(|x| Rule::parse(x, &start));
+        let exdates = event
+            .properties
+            .iter()
+            .filter(|x| x.name == "EXDATE")
+            .flat_map(|x| {
+                let tzid = param(x, "TZID");
+                x.value
+                    .as_deref()
+                    .unwrap_or_default()
+                    .split(',')
+                    .filter_map(|value| parse_stamp(value, tzid)?.resolve())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        Some(VEvent {
+            uid: find("UID").and_then(|x| x.value.clone()),
+            summary: find("SUMMARY")
+                .and_then(|x| x.value.as_deref())
+                .map(unescape)
+                .unwrap_or_default(),
+            location: find("LOCATION")
+                .and_then(|x| x.value.as_deref())
+                .map(unescape)
+                .filter(|x| !x.is_empty()),
+            start,
+            duration,
+            rule,
+            exdates,
+            recurrence_id: find("RECURRENCE-ID")
+                .and_then(stamp)
+                .and_then(|x| x.resolve()),
+        })
+    }
+}
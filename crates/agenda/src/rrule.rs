@@ -0,0 +1,406 @@
+// The subset of RFC 5545 recurrence rules calendar apps export for family
+// schedules: FREQ, INTERVAL, COUNT, UNTIL, BYDAY (with ordinals like 2MO or
+// -1FR), BYMONTHDAY and BYMONTH. Weeks start on monday.
+use super::parse::{parse_stamp, Stamp};
+use chrono::{Datelike, Days, Duration, NaiveDate, NaiveDateTime, Weekday};
+use log::warn;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    frequency: Frequency,
+    interval: u32,
+    count: Option<u32>,
+    /// wall clock time in the zone of the event
+    until: Option<NaiveDateTime>,
+    by_day: Vec<(Option<i32>, Weekday)>,
+    by_month_day: Vec<i32>,
+    by_month: Vec<u32>,
+}
+
+fn weekday(code: &str) -> Option<Weekday> {
+    Some(match code {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let next = match month {
+        12 => NaiveDate::from_ymd_opt(year + 1, 1, 1),
+        _ => NaiveDate::from_ymd_opt(year, month + 1, 1),
+    };
+    let first = NaiveDate::from_ymd_opt(year, month, 1);
+    match (first, next) {
+        (Some(first), Some(next)) => (next - first).num_days() as u32,
+        _ => 0,
+    }
+}
+
+impl Rule {
+    /// FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE;UNTIL=20241231T000000Z
+    pub fn parse(value: &str, start: &Stamp) -> Option<Rule> {
+        let mut rule = Rule {
+            frequency: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_month: Vec::new(),
+        };
+        let mut frequency = None;
+        for part in value.split(';') {
+            let Some((key, value)) = part.split_once('=') else {
+                continue;
+            };
+            let list = || value.split(',').map(|x| x.trim());
+            match key {
+                "FREQ" => {
+                    frequency = Some(match value {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => {
+                            warn!("unsupported recurrence: {}", value);
+                            return None;
+                        }
+                    })
+                }
+                "INTERVAL" => rule.interval = value.parse().ok()?,
+                "COUNT" => rule.count = value.parse().ok(),
+                "UNTIL" => {
+                    let until = parse_stamp(value, None)?;
+                    rule.until = Some(if until.all_day {
+                        until.naive.date().and_hms_opt(23, 59, 59)?
+                    } else {
+                        start.zone.localize(until.resolve()?)
+                    });
+                }
+                "BYDAY" => {
+                    for item in list() {
+                        let (ordinal, code) = item.split_at(item.len().saturating_sub(2));
+                        let ordinal = match ordinal {
+                            "" => None,
+                            x => Some(x.trim_start_matches('+').parse().ok()?),
+                        };
+                        rule.by_day.push((ordinal, weekday(code)?));
+                    }
+                }
+                "BYMONTHDAY" => rule.by_month_day = list().filter_map(|x| x.parse().ok()).collect(),
+                "BYMONTH" => rule.by_month = list().filter_map(|x| x.parse().ok()).collect(),
+                _ => {}
+            }
+        }
+        rule.frequency = frequency?;
+        rule.interval = rule.interval.max(1);
+        Some(rule)
+    }
+
+    /// days of a month matched by BYDAY and BYMONTHDAY, or `default` alone
+    fn month_days(&self, year: i32, month: u32, default: u32) -> Vec<u32> {
+        let length = days_in_month(year, month);
+        let month_days: Vec<u32> = self
+            .by_month_day
+            .iter()
+            .filter_map(|&day| match day {
+                1.. => Some(day as u32),
+                ..=-1 => (length as i32 + 1 + day).try_into().ok(),
+                0 => None,
+            })
+            .filter(|&day| day >= 1 && day <= length)
+            .collect();
+        let mut days = if self.by_day.is_empty() {
+            if month_days.is_empty() {
+                vec![default].into_iter().filter(|&x| x <= length).collect()
+            } else {
+                month_days
+            }
+        } else {
+            let mut days = Vec::new();
+            for &(ordinal, weekday) in self.by_day.iter() {
+                let matches: Vec<u32> = (1..=length)
+                    .filter(|&day| {
+                        NaiveDate::from_ymd_opt(year, month, day)
+                            .is_some_and(|x| x.weekday() == weekday)
+                    })
+                    .collect();
+                match ordinal {
+                    None => days.extend(matches),
+                    Some(n) if n > 0 => days.extend(matches.get(n as usize - 1)),
+                    Some(n) => days.extend(
+                        (matches.len() as i32 + n)
+                            .try_into()
+                            .ok()
+                            .and_then(|x: usize| matches.get(x)),
+                    ),
+                }
+            }
+            if !month_days.is_empty() {
+                days.retain(|x| month_days.contains(x));
+            }
+            days
+        };
+        days.sort_unstable();
+        days.dedup();
+        days
+    }
+
+    /// the first day of the `period`-th period and the days it holds, none
+    /// once the period is out of the range of dates
+    fn period(&self, start: NaiveDate, period: u32) -> Option<(NaiveDate, Vec<NaiveDate>)> {
+        let step = period.checked_mul(self.interval)? as u64;
+        Some(match self.frequency {
+            Frequency::Daily => {
+                let day = start.checked_add_days(Days::new(step))?;
+                let matched = (self.by_month.is_empty() || self.by_month.contains(&day.month()))
+                    && (self.by_day.is_empty()
+                        || self.by_day.iter().any(|(_, x)| *x == day.weekday()))
+                    && (self.by_month_day.is_empty()
+                        || self.by_month_day.contains(&(day.day() as i32)));
+                (day, if matched { vec![day] } else { vec![] })
+            }
+            Frequency::Weekly => {
+                let monday = (start
+                    - Duration::days(start.weekday().num_days_from_monday() as i64))
+                .checked_add_days(Days::new(step.checked_mul(7)?))?;
+                let mut weekdays: Vec<Weekday> = self.by_day.iter().map(|x| x.1).collect();
+                if weekdays.is_empty() {
+                    weekdays.push(start.weekday());
+                }
+                let mut days: Vec<NaiveDate> = weekdays
+                    .into_iter()
+                    .map(|x| monday + Duration::days(x.num_days_from_monday() as i64))
+                    .collect();
+                days.sort_unstable();
+                days.dedup();
+                (monday, days)
+            }
+            Frequency::Monthly => {
+                let months = start.month0() as u64 + step;
+                let year = start.year().checked_add((months / 12).try_into().ok()?)?;
+                let month = (months % 12) as u32 + 1;
+                let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+                if !self.by_month.is_empty() && !self.by_month.contains(&month) {
+                    return Some((first, vec![]));
+                }
+                let days = self
+                    .month_days(year, month, start.day())
+                    .into_iter()
+                    .filter_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+                    .collect();
+                (first, days)
+            }
+            Frequency::Yearly => {
+                let year = start.year().checked_add(step.try_into().ok()?)?;
+                let first = NaiveDate::from_ymd_opt(year, 1, 1)?;
+                let months = if self.by_month.is_empty() {
+                    vec![start.month()]
+                } else {
+                    self.by_month.clone()
+                };
+                let mut days: Vec<NaiveDate> = months
+                    .into_iter()
+                    .flat_map(|month| {
+                        self.month_days(year, month, start.day())
+                            .into_iter()
+                            .filter_map(move |day| NaiveDate::from_ymd_opt(year, month, day))
+                    })
+                    .collect();
+                days.sort_unstable();
+                (first, days)
+            }
+        })
+    }
+
+    /// starts of all occurrences up to `end`, in wall clock time of the zone
+    /// of the event
+    pub fn occurrences(&self, start: NaiveDateTime, end: NaiveDateTime) -> Vec<NaiveDateTime> {
+        let mut result = Vec::new();
+        let mut emitted = 0;
+        for period in 0.. {
+            let Some((first, days)) = self.period(start.date(), period) else {
+                break;
+            };
+            if first.and_time(start.time()) > end {
+                break;
+            }
+            for day in days {
+                let time = day.and_time(start.time());
+                if time < start {
+                    continue;
+                }
+                if time > end
+                    || self.until.is_some_and(|x| time > x)
+                    || self.count.is_some_and(|x| emitted >= x)
+                {
+                    return result;
+                }
+                emitted += 1;
+                result.push(time);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{expand, read};
+    use super::*;
+    use chrono::{DateTime, Local};
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn local(value: &str) -> DateTime<Local> {
+        at(value).and_local_timezone(Local).earliest().unwrap()
+    }
+
+    /// starts of `rule` from 2024-01-01 09:00 (a monday) to 2025-12-31
+    fn starts(rule: &str) -> Vec<String> {
+        let start = parse_stamp("20240101T090000", None).unwrap();
+        Rule::parse(rule, &start)
+            .unwrap()
+            .occurrences(start.naive, at("2025-12-31 00:00"))
+            .iter()
+            .map(|x| x.format("%Y-%m-%d %H:%M").to_string())
+            .collect()
+    }
+
+    /// summaries and starts of a calendar in march 2024
+    fn agenda(events: &str) -> Vec<String> {
+        let ics = format!(
+            "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n{}END:VCALENDAR\r\n",
+            events.replace('\n', "\r\n")
+        );
+        let events = read(ics.as_bytes()).unwrap();
+        let mut events = expand(
+            &events,
+            "test",
+            local("2024-03-01 00:00"),
+            local("2024-04-01 00:00"),
+        );
+        events.sort_by_key(|x| x.start);
+        events
+            .iter()
+            .map(|x| format!("{} {}", x.start.format("%m-%d %H:%M"), x.summary))
+            .collect()
+    }
+
+    #[test]
+    fn count() {
+        assert_eq!(
+            starts("FREQ=WEEKLY;BYDAY=MO,TH;COUNT=3"),
+            ["2024-01-01 09:00", "2024-01-04 09:00", "2024-01-08 09:00"]
+        );
+    }
+
+    #[test]
+    fn until() {
+        assert_eq!(
+            starts("FREQ=DAILY;INTERVAL=2;UNTIL=20240105T090000"),
+            ["2024-01-01 09:00", "2024-01-03 09:00", "2024-01-05 09:00"]
+        );
+        assert_eq!(
+            starts("FREQ=MONTHLY;UNTIL=20240301"),
+            ["2024-01-01 09:00", "2024-02-01 09:00", "2024-03-01 09:00"]
+        );
+    }
+
+    #[test]
+    fn by_day_ordinals() {
+        assert_eq!(
+            starts("FREQ=MONTHLY;BYDAY=2MO;COUNT=3"),
+            ["2024-01-08 09:00", "2024-02-12 09:00", "2024-03-11 09:00"]
+        );
+        assert_eq!(
+            starts("FREQ=MONTHLY;BYDAY=-1FR;COUNT=3"),
+            ["2024-01-26 09:00", "2024-02-23 09:00", "2024-03-29 09:00"]
+        );
+        assert_eq!(
+            starts("FREQ=YEARLY;BYMONTH=5;BYDAY=2SU;COUNT=2"),
+            ["2024-05-12 09:00", "2025-05-11 09:00"]
+        );
+    }
+
+    #[test]
+    fn overflow() {
+        for rule in [
+            "FREQ=DAILY;INTERVAL=200000000",
+            "FREQ=WEEKLY;INTERVAL=4000000000",
+            "FREQ=MONTHLY;INTERVAL=4000000000",
+            "FREQ=YEARLY;INTERVAL=4000000000",
+        ] {
+            assert_eq!(starts(rule), ["2024-01-01 09:00"], "{}", rule);
+        }
+        let start = parse_stamp("20240101T090000", None).unwrap();
+        let rule = Rule::parse("FREQ=YEARLY;INTERVAL=100000", &start).unwrap();
+        assert_eq!(
+            rule.occurrences(start.naive, NaiveDateTime::MAX).len(),
+            3,
+            "years past the range of dates"
+        );
+    }
+
+    #[test]
+    fn exdate() {
+        assert_eq!(
+            agenda(
+                "BEGIN:VEVENT
+UID:piano
+SUMMARY:piano
+DTSTART:20240304T170000
+DURATION:PT1H
+RRULE:FREQ=WEEKLY;COUNT=4
+EXDATE:20240311T170000,20240318T170000
+END:VEVENT
+"
+            ),
+            ["03-04 17:00 piano", "03-25 17:00 piano"]
+        );
+    }
+
+    #[test]
+    fn recurrence_id() {
+        assert_eq!(
+            agenda(
+                "BEGIN:VEVENT
+UID:swim
+SUMMARY:swim
+DTSTART:20240305T180000
+DURATION:PT1H
+RRULE:FREQ=WEEKLY;UNTIL=20240319T180000
+END:VEVENT
+BEGIN:VEVENT
+UID:swim
+SUMMARY:swim (pool closed early)
+RECURRENCE-ID:20240312T180000
+DTSTART:20240312T160000
+DURATION:PT1H
+END:VEVENT
+"
+            ),
+            [
+                "03-05 18:00 swim",
+                "03-12 16:00 swim (pool closed early)",
+                "03-19 18:00 swim"
+            ]
+        );
+    }
+}
@@ -0,0 +1,124 @@
+// Statutory holidays and make-up workdays (调休) of mainland China, read
+// from yearly files in the format of https://github.com/NateScarlet/holiday-cn
+// named `<year>.json`, e.g.
+//
+// {"year": 2024, "days": [{"name": "春节", "date": "2024-02-10", "isOffDay": true}, ...]}
+//
+// Files are looked up on every query and reloaded when modified, so next
+// year's file can be dropped in without a restart.
+use chrono::{Datelike, NaiveDate, Weekday};
+use log::{info, warn};
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Workday,
+    Weekend,
+    /// statutory day off
+    Holiday,
+    /// weekend turned into a workday to make up for a holiday
+    Adjusted,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Day {
+    pub date: NaiveDate,
+    pub kind: Kind,
+    /// name of the holiday a holiday or adjusted workday belongs to
+    pub name: Option<String>,
+}
+
+impl Day {
+    pub fn is_workday(&self) -> bool {
+        matches!(self.kind, Kind::Workday | Kind::Adjusted)
+    }
+
+    pub fn is_holiday(&self) -> bool {
+        self.kind == Kind::Holiday
+    }
+}
+
+#[derive(Deserialize)]
+struct File {
+    days: Vec<Entry>,
+}
+
+#[derive(Deserialize)]
+struct Entry {
+    name: String,
+    date: NaiveDate,
+    #[serde(rename = "isOffDay")]
+    is_off_day: bool,
+}
+
+struct Year {
+    modified: Option<SystemTime>,
+    days: HashMap<NaiveDate, Entry>,
+}
+
+static PATH: OnceCell<PathBuf> = OnceCell::new();
+static YEARS: Lazy<Mutex<HashMap<i32, Year>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// directory of the yearly files, every day falls back to the plain
+/// weekday rule until this is called
+pub fn init(path: &str) {
+    PATH.get_or_init(|| {
+        info!("holiday path: {}", path);
+        PathBuf::from(path)
+    });
+}
+
+fn load(path: &PathBuf) -> Result<HashMap<NaiveDate, Entry>, String> {
+    let raw = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let file: File = serde_json::from_str(&raw).map_err(|e| e.to_string())?;
+    Ok(file.days.into_iter().map(|x| (x.date, x)).collect())
+}
+
+fn lookup(date: NaiveDate) -> Option<(String, bool)> {
+    let path = PATH.get()?.join(format!("{}.json", date.year()));
+    let modified = std::fs::metadata(&path).and_then(|x| x.modified()).ok();
+    let mut years = YEARS.lock().unwrap();
+    let outdated = years
+        .get(&date.year())
+        .is_none_or(|year| year.modified != modified);
+    if outdated {
+        let days = match modified {
+            Some(_) => load(&path).unwrap_or_else(|e| {
+                warn!("failed to load {}: {}", path.display(), e);
+                HashMap::new()
+            }),
+            None => {
+                warn!("{} not found, only weekends are days off", path.display());
+                HashMap::new()
+            }
+        };
+        years.insert(date.year(), Year { modified, days });
+    }
+    years
+        .get(&date.year())?
+        .days
+        .get(&date)
+        .map(|x| (x.name.clone(), x.is_off_day))
+}
+
+pub fn query(date: NaiveDate) -> Day {
+    let (kind, name) = match lookup(date) {
+        Some((name, true)) => (Kind::Holiday, Some(name)),
+        Some((name, false)) => (Kind::Adjusted, Some(name)),
+        None => match date.weekday() {
+            Weekday::Sat | Weekday::Sun => (Kind::Weekend, None),
+            _ => (Kind::Workday, None),
+        },
+    };
+    Day { date, kind, name }
+}
+
+pub fn is_workday(date: NaiveDate) -> bool {
+    query(date).is_workday()
+}
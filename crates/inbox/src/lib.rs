@@ -7,6 +7,7 @@ mod new;
 
 extern crate rocket;
 use drop::remove_expired;
+pub use list::{files, newest};
 use rocket::{figment::Figment, Build, Rocket};
 
 pub async fn build(
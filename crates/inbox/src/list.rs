@@ -1,8 +1,10 @@
 use crate::drop::remove_expired;
 
-use super::api::PREFIX;
-use super::metadata::Metadata;
+use super::api::{PATH, PREFIX};
+use super::metadata::{Kind, Metadata};
 use rocket::{figment::Figment, get, routes, serde::json::Json, Build, Rocket};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use utils::database::get_db;
 
 #[get("/list")]
@@ -28,3 +30,37 @@ pub async fn build(
 ) -> Result<Rocket<Build>, anyhow::Error> {
     Ok(build.mount(base, routes![list]))
 }
+
+/// original name and stored path of every unexpired file
+pub fn files() -> Vec<(String, PathBuf)> {
+    let Some(path) = PATH.get() else {
+        return Vec::new();
+    };
+    let now = SystemTime::now();
+    get_db()
+        .scan_prefix(PREFIX)
+        .filter_map(|x| x.ok())
+        .filter_map(|x| Metadata::try_from(&x.1).ok())
+        .filter(|x| matches!(x.kind, Kind::File) && x.expiration > now)
+        .map(|x| (x.name, Path::new(path).join(x.id)))
+        .collect()
+}
+
+/// id of the most recently stored public item
+pub fn newest() -> Option<String> {
+    let path = PATH.get()?;
+    let now = SystemTime::now();
+    get_db()
+        .scan_prefix(PREFIX)
+        .filter_map(|x| x.ok())
+        .filter_map(|x| Metadata::try_from(&x.1).ok())
+        .filter(|x| x.public && x.expiration > now)
+        .filter_map(|x| {
+            let stored = std::fs::metadata(Path::new(path).join(&x.id))
+                .and_then(|x| x.modified())
+                .ok()?;
+            Some((stored, x.id))
+        })
+        .max()
+        .map(|x| x.1)
+}
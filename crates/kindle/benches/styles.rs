@@ -0,0 +1,60 @@
+// Render time of every builtin style with the fixtures of the snapshot tests.
+//
+// cargo bench -p kindle --bench styles
+use chrono::{FixedOffset, TimeZone};
+use criterion::{criterion_group, criterion_main, Criterion};
+use kindle::Context;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const STYLES: [&str; 6] = ["alpha", "bravo", "charlie", "delta", "echo", "night"];
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+async fn context() -> Context {
+    let font = fixture("Tuffy.ttf").to_str().unwrap().to_string();
+    kindle::load_fonts(HashMap::from(
+        ["main", "weather", "status"].map(|x| (x.to_string(), vec![font.clone()])),
+    ));
+    kindle::set_quotes(Some(fixture("quotes.tsv").to_str().unwrap().to_string()));
+    let weather = std::fs::read_to_string(fixture("weather.json")).unwrap();
+    qweather::load_fixture(&serde_json::from_str(&weather).unwrap())
+        .await
+        .unwrap();
+    let now = FixedOffset::east_opt(8 * 3600)
+        .unwrap()
+        .with_ymd_and_hms(2024, 10, 1, 8, 30, 0)
+        .unwrap();
+    let mut context = Context {
+        battery: Some(77),
+        now: Some(now),
+        ..Default::default()
+    };
+    context.fetch().await;
+    context
+}
+
+fn styles(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let context = runtime.block_on(context());
+    let mut group = c.benchmark_group("styles");
+    for style in STYLES {
+        group.bench_function(style, |b| {
+            b.iter(|| {
+                runtime
+                    .block_on(kindle::factory(Some(style), &context))
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, styles);
+criterion_main!(benches);
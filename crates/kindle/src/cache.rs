@@ -0,0 +1,77 @@
+use super::dither::Dither;
+use super::encode::Format;
+use super::history::Reading;
+use super::locale::Locale;
+use super::quote::Quote;
+use once_cell::sync::Lazy;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+const CAPACITY: usize = 64;
+
+/// everything a rendered screen depends on, two requests with the same key
+/// get the same bytes
+#[derive(Hash)]
+pub struct RenderKey<'a> {
+    /// its timezone and battery history show on screen
+    pub device: &'a str,
+    pub style: Option<&'a str>,
+    pub profile: Option<&'a str>,
+    pub locale: Locale,
+    /// minutes since epoch of the displayed time
+    pub minute: i64,
+    pub battery: Option<usize>,
+    /// drives the charging sign and time to empty
+    pub readings: &'a [Reading],
+    pub weather: u64,
+    pub holiday: Option<&'a holiday::Day>,
+    pub agenda: &'a [agenda::Event],
+    /// the quote of the day, which changes when quotes are added
+    pub quote: Option<&'a Quote>,
+    pub levels: Option<u32>,
+    pub dither: Option<Dither>,
+    pub format: Format,
+    pub invert: bool,
+}
+
+impl RenderKey<'_> {
+    pub fn tag(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+struct Entry {
+    minute: i64,
+    data: Arc<Vec<u8>>,
+}
+
+static CACHE: Lazy<Mutex<HashMap<u64, Entry>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn get_render(key: &RenderKey) -> Option<Arc<Vec<u8>>> {
+    let cache = CACHE.lock().unwrap();
+    cache.get(&key.tag()).map(|x| x.data.clone())
+}
+
+pub fn put_render(key: &RenderKey, data: Arc<Vec<u8>>) {
+    let mut cache = CACHE.lock().unwrap();
+    if cache.len() >= CAPACITY {
+        let oldest = cache
+            .iter()
+            .min_by_key(|(_, entry)| entry.minute)
+            .map(|(tag, _)| *tag);
+        if let Some(oldest) = oldest {
+            cache.remove(&oldest);
+        }
+    }
+    cache.insert(
+        key.tag(),
+        Entry {
+            minute: key.minute,
+            data,
+        },
+    );
+}
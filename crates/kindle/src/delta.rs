@@ -0,0 +1,75 @@
+use super::shared::*;
+use super::widgets::*;
+use anyhow::Result;
+use image::{GrayImage, Luma};
+
+pub fn generate(img: &mut GrayImage, context: &Context) -> Result<()> {
+    let frame = Frame::new(img, 600, 800);
+    let style = TextStyle {
+        font: "main",
+        color: Luma([0]),
+        align: (AlignHorizontal::Center, AlignVertical::Center),
+    };
+
+    compose(
+        img,
+        context,
+        &[
+            (frame.rect(0, 0, 600, 800), &Fill { color: Luma([255]) }),
+            (
+                frame.rect(0, -50, 600, 400),
+                &DayWidget {
+                    style,
+                    day: DayStyle::Arabic,
+                },
+            ),
+            (
+                frame.rect(0, 340, 600, 60),
+                &LunarWidget { style, year: true },
+            ),
+            (
+                frame.rect(0, 420, 600, 56),
+                &FestivalWidget {
+                    style: TextStyle {
+                        color: Luma([96]),
+                        ..style
+                    },
+                },
+            ),
+            (
+                frame.rect(0, 520, 600, 140),
+                &WeekdayWidget {
+                    style,
+                    short: false,
+                    background: None,
+                },
+            ),
+            (frame.rect(0, 680, 600, 48), &HolidayWidget { style }),
+            (
+                frame.rect(0, 20, 600 - 25, 36),
+                &BatteryWidget {
+                    style: TextStyle {
+                        align: (AlignHorizontal::Right, AlignVertical::Top),
+                        ..style
+                    },
+                    text: |battery, locale| locale.battery(battery),
+                },
+            ),
+            (
+                frame.rect(0, 800 - 20 - 18, 600, 18),
+                &UpdateTimeWidget {
+                    style: TextStyle {
+                        color: Luma([128]),
+                        align: (AlignHorizontal::Center, AlignVertical::Bottom),
+                        ..style
+                    },
+                    text: |now, locale| {
+                        format!("{}{}", locale.updated(), now.format("%Y-%m-%d %H:%M"))
+                    },
+                },
+            ),
+        ],
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,156 @@
+use super::locale::Locale;
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use log::{error, info};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// requests without `?device=` belong to this one
+pub const DEFAULT_DEVICE: &str = "kindle";
+const PREFIX: &str = "kindle/device/";
+
+/// a client of the kindle route, settings come from `kindle.devices` and the
+/// rest is remembered in sled
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Device {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub style: Option<String>,
+    /// zh-CN, en or ja, `kindle.locale` if absent
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// IANA name such as Asia/Shanghai, local time of the server if absent
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// unix timestamp in seconds
+    #[serde(default)]
+    pub last_seen: Option<i64>,
+    #[serde(default)]
+    pub battery: Option<usize>,
+    /// whether the low battery notification went out for this discharge
+    #[serde(default)]
+    pub low_battery: bool,
+}
+
+impl Device {
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.id)
+    }
+
+    fn tz(&self) -> Option<Tz> {
+        self.timezone.as_ref()?.parse().ok()
+    }
+
+    pub fn now(&self) -> DateTime<FixedOffset> {
+        match self.tz() {
+            Some(tz) => Utc::now().with_timezone(&tz).fixed_offset(),
+            None => Local::now().fixed_offset(),
+        }
+    }
+
+    /// wall clock time of the device to an instant
+    pub fn localize(&self, naive: NaiveDateTime) -> Option<DateTime<FixedOffset>> {
+        match self.tz() {
+            Some(tz) => tz
+                .from_local_datetime(&naive)
+                .earliest()
+                .map(|x| x.fixed_offset()),
+            None => Local
+                .from_local_datetime(&naive)
+                .earliest()
+                .map(|x| x.fixed_offset()),
+        }
+    }
+
+    /// sled key of the battery history
+    pub fn history_key(&self) -> String {
+        format!("kindle/battery/{}", self.id)
+    }
+
+    /// configured settings win over remembered ones
+    fn merge(mut self, configured: &Device) -> Device {
+        self.name = configured.name.clone().or(self.name);
+        self.profile = configured.profile.clone().or(self.profile);
+        self.style = configured.style.clone().or(self.style);
+        self.locale = configured.locale.clone().or(self.locale);
+        self.timezone = configured.timezone.clone().or(self.timezone);
+        self
+    }
+}
+
+static DEVICES: OnceCell<HashMap<String, Device>> = OnceCell::new();
+
+pub fn load_devices(devices: HashMap<String, Device>) {
+    DEVICES.get_or_init(|| {
+        let empty = |x: Option<String>| x.filter(|x| !x.is_empty());
+        devices
+            .into_iter()
+            .map(|(id, device)| {
+                let device = Device {
+                    id: id.clone(),
+                    name: empty(device.name),
+                    profile: empty(device.profile),
+                    style: empty(device.style),
+                    locale: empty(device.locale),
+                    timezone: empty(device.timezone),
+                    ..Default::default()
+                };
+                if device.timezone.is_some() && device.tz().is_none() {
+                    error!("unknown timezone of device {}: {:?}", id, device.timezone);
+                }
+                if let Some(Err(e)) = device.locale.as_deref().map(str::parse::<Locale>) {
+                    error!("device {}: {}", id, e);
+                }
+                info!("kindle device {}: {:?}", id, device);
+                (id, device)
+            })
+            .collect()
+    });
+}
+
+/// a device seen for the first time starts with default settings
+pub fn get_device(id: &str) -> Device {
+    let db = utils::database::Db::new();
+    let stored: Option<Device> = db.get(&format!("{}{}", PREFIX, id)).ok().flatten();
+    match stored {
+        Some(stored) => stored.merge(&configured_device(id)),
+        None => configured_device(id),
+    }
+}
+
+/// settings of `kindle.devices` alone, without touching sled
+pub fn configured_device(id: &str) -> Device {
+    DEVICES
+        .get()
+        .and_then(|x| x.get(id))
+        .cloned()
+        .unwrap_or_else(|| Device {
+            id: id.to_string(),
+            ..Default::default()
+        })
+}
+
+pub fn save_device(device: &Device) -> anyhow::Result<()> {
+    let db = utils::database::Db::new();
+    db.set(&format!("{}{}", PREFIX, device.id), device)
+}
+
+/// configured and seen devices by id
+pub fn list_devices() -> Vec<Device> {
+    let mut ids: Vec<String> = utils::database::get_db()
+        .scan_prefix(PREFIX)
+        .keys()
+        .filter_map(|x| x.ok())
+        .filter_map(|x| String::from_utf8(x[PREFIX.len()..].to_vec()).ok())
+        .collect();
+    ids.extend(DEVICES.get().into_iter().flat_map(|x| x.keys().cloned()));
+    ids.sort();
+    ids.dedup();
+    ids.iter().map(|x| get_device(x)).collect()
+}
@@ -0,0 +1,97 @@
+use anyhow::anyhow;
+use image::GrayImage;
+use serde::Deserialize;
+use std::str::FromStr;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum Dither {
+    None,
+    FloydSteinberg,
+    Ordered,
+}
+
+impl FromStr for Dither {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Dither::None),
+            "floyd-steinberg" => Ok(Dither::FloydSteinberg),
+            "ordered" => Ok(Dither::Ordered),
+            _ => Err(anyhow!("unknown dither = {}", s)),
+        }
+    }
+}
+
+const BAYER_8X8: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// reduce the image to `levels` evenly spaced grays, e.g. 2, 4 or 16 for
+/// e-ink panels
+pub fn quantize(img: &mut GrayImage, levels: u32, dither: Dither) {
+    let levels = levels.clamp(2, 256);
+    if levels == 256 {
+        return;
+    }
+    let step = 255.0 / (levels - 1) as f32;
+    let nearest = |value: f32| -> u8 {
+        let index = (value / step).round().clamp(0.0, (levels - 1) as f32);
+        (index * step).round() as u8
+    };
+    // snap the faint coverage left around glyphs, or its error accumulates
+    // into stray dots on flat areas
+    let tolerance = step / 16.0;
+    for pixel in img.pixels_mut() {
+        let value = pixel.0[0] as f32;
+        let level = nearest(value);
+        if (value - level as f32).abs() <= tolerance {
+            pixel.0[0] = level;
+        }
+    }
+    match dither {
+        Dither::None => {
+            for pixel in img.pixels_mut() {
+                pixel.0[0] = nearest(pixel.0[0] as f32);
+            }
+        }
+        Dither::Ordered => {
+            for (x, y, pixel) in img.enumerate_pixels_mut() {
+                let threshold = (BAYER_8X8[y as usize % 8][x as usize % 8] as f32 + 0.5) / 64.0;
+                pixel.0[0] = nearest(pixel.0[0] as f32 + (threshold - 0.5) * step);
+            }
+        }
+        Dither::FloydSteinberg => {
+            let (width, height) = (img.width() as usize, img.height() as usize);
+            let mut buffer: Vec<f32> = img.pixels().map(|x| x.0[0] as f32).collect();
+            for y in 0..height {
+                for x in 0..width {
+                    let index = y * width + x;
+                    let old = buffer[index];
+                    let new = nearest(old);
+                    let error = old - new as f32;
+                    img.get_pixel_mut(x as u32, y as u32).0[0] = new;
+                    if x + 1 < width {
+                        buffer[index + 1] += error * 7.0 / 16.0;
+                    }
+                    if y + 1 < height {
+                        if x > 0 {
+                            buffer[index + width - 1] += error * 3.0 / 16.0;
+                        }
+                        buffer[index + width] += error * 5.0 / 16.0;
+                        if x + 1 < width {
+                            buffer[index + width + 1] += error * 1.0 / 16.0;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use super::shared::*;
+use super::widgets::*;
+use anyhow::Result;
+use image::{GrayImage, Luma};
+
+pub fn generate(img: &mut GrayImage, context: &Context) -> Result<()> {
+    let frame = Frame::new(img, 600, 800);
+    let style = TextStyle {
+        font: "main",
+        color: Luma([0]),
+        align: (AlignHorizontal::Center, AlignVertical::Center),
+    };
+
+    compose(
+        img,
+        context,
+        &[
+            (frame.rect(0, 0, 600, 800), &Fill { color: Luma([255]) }),
+            (frame.rect(0, 30, 600, 64), &MonthTitleWidget { style }),
+            (
+                frame.rect(20, 120, 560, 530),
+                &MonthWidget {
+                    style,
+                    week_start: super::week_start(),
+                    week_numbers: true,
+                    shade: Luma([216]),
+                },
+            ),
+            (
+                frame.rect(0, 670, 600, 52),
+                &LunarWidget { style, year: true },
+            ),
+            (
+                frame.rect(30, 740, 270, 36),
+                &FestivalWidget {
+                    style: TextStyle {
+                        align: (AlignHorizontal::Left, AlignVertical::Center),
+                        ..style
+                    },
+                },
+            ),
+            (
+                frame.rect(300, 740, 270, 36),
+                &HolidayWidget {
+                    style: TextStyle {
+                        align: (AlignHorizontal::Right, AlignVertical::Center),
+                        ..style
+                    },
+                },
+            ),
+            (
+                frame.rect(0, 20, 600 - 25, 24),
+                &BatteryWidget {
+                    style: TextStyle {
+                        font: "status",
+                        color: Luma([128]),
+                        align: (AlignHorizontal::Right, AlignVertical::Top),
+                    },
+                    text: |battery, _| format!("{}%", battery),
+                },
+            ),
+        ],
+    )?;
+
+    Ok(())
+}
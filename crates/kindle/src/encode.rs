@@ -0,0 +1,72 @@
+use anyhow::{anyhow, Result};
+use image::{GrayImage, ImageOutputFormat};
+use serde::Deserialize;
+use std::io::Cursor;
+use std::str::FromStr;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Png,
+    Bmp,
+    /// packed 1 bit per pixel, row-major, msb first, 1 is white
+    Raw1,
+    /// packed 4 bits per pixel, row-major, high nibble first, 15 is white
+    Raw4,
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "png" => Ok(Format::Png),
+            "bmp" => Ok(Format::Bmp),
+            "raw1" => Ok(Format::Raw1),
+            "raw4" => Ok(Format::Raw4),
+            _ => Err(anyhow!("unknown format = {}", s)),
+        }
+    }
+}
+
+impl Format {
+    /// gray levels a packed framebuffer can hold
+    pub fn levels(&self) -> Option<u32> {
+        match self {
+            Format::Png | Format::Bmp => None,
+            Format::Raw1 => Some(2),
+            Format::Raw4 => Some(16),
+        }
+    }
+}
+
+fn pack(img: &GrayImage, bits: u32) -> Vec<u8> {
+    let per_byte = 8 / bits;
+    let stride = img.width().div_ceil(per_byte) as usize;
+    let mut buffer = vec![0u8; stride * img.height() as usize];
+    for (x, y, pixel) in img.enumerate_pixels() {
+        let value = pixel.0[0] >> (8 - bits);
+        let shift = 8 - bits * (x % per_byte + 1);
+        buffer[y as usize * stride + (x / per_byte) as usize] |= value << shift;
+    }
+    buffer
+}
+
+pub fn encode(img: &GrayImage, format: Format, invert: bool) -> Result<Vec<u8>> {
+    let mut img = img.clone();
+    if invert {
+        image::imageops::invert(&mut img);
+    }
+    match format {
+        Format::Png | Format::Bmp => {
+            let format = match format {
+                Format::Bmp => ImageOutputFormat::Bmp,
+                _ => ImageOutputFormat::Png,
+            };
+            let mut buffer: Vec<u8> = Vec::new();
+            img.write_to(&mut Cursor::new(&mut buffer), format)?;
+            Ok(buffer)
+        }
+        Format::Raw1 => Ok(pack(&img, 1)),
+        Format::Raw4 => Ok(pack(&img, 4)),
+    }
+}
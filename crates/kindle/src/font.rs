@@ -0,0 +1,211 @@
+// Named font stacks: every character is drawn with the first font of the
+// stack that has a glyph for it, so a latin status font can fall back to a
+// CJK one for weather texts. Characters no font has are drawn as the missing
+// glyph of the first font and logged once.
+//
+// Pens are rounded to whole pixels, so a glyph is rasterized once per scale
+// and reused from the cache of its stack.
+use image::Pixel;
+use imageproc::drawing::Canvas;
+use imageproc::rect::Rect;
+use log::warn;
+use once_cell::sync::Lazy;
+use rusttype::{point, Font, GlyphId, Scale};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+/// (stack, character) pairs already warned about
+static MISSING: Lazy<Mutex<HashSet<(String, char)>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// rasters kept per stack before the cache starts over
+const CACHE_SIZE: usize = 4096;
+
+/// coverage of a glyph drawn with its pen at the origin of the baseline
+struct Raster {
+    left: i32,
+    top: i32,
+    width: usize,
+    coverage: Vec<u8>,
+}
+
+/// by (character, scale), `None` for blank glyphs
+type Rasters = HashMap<(char, u32, u32), Option<Arc<Raster>>>;
+
+/// a glyph placed on the line with its pen rounded to whole pixels
+struct Placed {
+    font: usize,
+    c: char,
+    x: i32,
+    y: i32,
+}
+
+pub struct FontStack {
+    name: String,
+    fonts: Vec<Font<'static>>,
+    glyphs: Mutex<Rasters>,
+}
+
+impl FontStack {
+    /// `fonts` must not be empty
+    pub fn new(name: &str, fonts: Vec<Font<'static>>) -> FontStack {
+        assert!(!fonts.is_empty(), "font stack {} is empty", name);
+        FontStack {
+            name: name.to_string(),
+            fonts,
+            glyphs: Mutex::new(HashMap::new()),
+        }
+    }
+    /// the first font with a glyph for `c`
+    fn find(&self, c: char) -> Option<usize> {
+        self.fonts
+            .iter()
+            .position(|x| x.glyph(c).id() != GlyphId(0))
+    }
+
+    fn warn_missing(&self, missing: Vec<char>) {
+        let mut warned = MISSING.lock().unwrap();
+        let missing: Vec<String> = missing
+            .into_iter()
+            .filter(|c| warned.insert((self.name.clone(), *c)))
+            .map(|c| format!("U+{:04X} {}", c as u32, c))
+            .collect();
+        if !missing.is_empty() {
+            warn!("font {} has no glyph for {}", self.name, missing.join(", "));
+        }
+    }
+
+    /// glyphs on a baseline at the ascent of the first font, kerned when
+    /// neighbours come from the same font
+    fn layout(&self, scale: Scale, text: &str) -> Vec<Placed> {
+        let ascent = self.fonts[0].v_metrics(scale).ascent.round() as i32;
+        let mut caret = 0.0f32;
+        let mut last: Option<(usize, GlyphId)> = None;
+        let mut glyphs = Vec::new();
+        let mut missing = Vec::new();
+        for c in text.chars() {
+            let index = self.find(c).unwrap_or_else(|| {
+                if !c.is_control() {
+                    missing.push(c);
+                }
+                0
+            });
+            let font = &self.fonts[index];
+            let glyph = font.glyph(c).scaled(scale);
+            if let Some((last_index, last_id)) = last {
+                if last_index == index {
+                    caret += font.pair_kerning(scale, last_id, glyph.id());
+                }
+            }
+            glyphs.push(Placed {
+                font: index,
+                c,
+                x: caret.round() as i32,
+                y: ascent,
+            });
+            caret += glyph.h_metrics().advance_width;
+            last = Some((index, glyph.id()));
+        }
+        if !missing.is_empty() {
+            self.warn_missing(missing);
+        }
+        glyphs
+    }
+
+    /// pixel bounds of a glyph with its pen at the origin, without rasterizing
+    fn glyph_bounds(&self, scale: Scale, placed: &Placed) -> Option<Rect> {
+        let bb = self.fonts[placed.font]
+            .glyph(placed.c)
+            .scaled(scale)
+            .positioned(point(0.0, 0.0))
+            .pixel_bounding_box()?;
+        Some(
+            Rect::at(bb.min.x + placed.x, bb.min.y + placed.y)
+                .of_size(bb.width() as u32, bb.height() as u32),
+        )
+    }
+
+    fn raster(&self, scale: Scale, placed: &Placed) -> Option<Arc<Raster>> {
+        let key = (placed.c, scale.x.to_bits(), scale.y.to_bits());
+        let mut glyphs = self.glyphs.lock().unwrap();
+        if let Some(raster) = glyphs.get(&key) {
+            return raster.clone();
+        }
+        if glyphs.len() >= CACHE_SIZE {
+            glyphs.clear();
+        }
+        let glyph = self.fonts[placed.font]
+            .glyph(placed.c)
+            .scaled(scale)
+            .positioned(point(0.0, 0.0));
+        let raster = glyph.pixel_bounding_box().map(|bb| {
+            let width = bb.width() as usize;
+            let mut coverage = vec![0; width * bb.height() as usize];
+            glyph.draw(|x, y, v| {
+                coverage[y as usize * width + x as usize] = (v * 255.0).round() as u8
+            });
+            Arc::new(Raster {
+                left: bb.min.x,
+                top: bb.min.y,
+                width,
+                coverage,
+            })
+        });
+        glyphs.insert(key, raster.clone());
+        raster
+    }
+
+    /// the inked pixels relative to the origin, `None` for blank text
+    pub fn bounds(&self, scale: Scale, text: &str) -> Option<Rect> {
+        self.layout(scale, text)
+            .iter()
+            .filter_map(|x| self.glyph_bounds(scale, x))
+            .reduce(|a, b| {
+                let (left, top) = (a.left().min(b.left()), a.top().min(b.top()));
+                let (right, bottom) = (a.right().max(b.right()), a.bottom().max(b.bottom()));
+                Rect::at(left, top).of_size((right - left + 1) as u32, (bottom - top + 1) as u32)
+            })
+    }
+
+    /// width and height of the inked pixels counted from the origin
+    pub fn text_size(&self, scale: Scale, text: &str) -> (i32, i32) {
+        self.bounds(scale, text).map_or((0, 0), |x| {
+            ((x.right() + 1).max(0), (x.bottom() + 1).max(0))
+        })
+    }
+
+    /// blend the text onto `canvas` with its origin at `x`, `y`
+    pub fn draw_text_mut<C>(
+        &self,
+        canvas: &mut C,
+        color: C::Pixel,
+        x: i32,
+        y: i32,
+        scale: Scale,
+        text: &str,
+    ) where
+        C: Canvas,
+        C::Pixel: Pixel<Subpixel = u8>,
+    {
+        let (width, height) = (canvas.width() as i32, canvas.height() as i32);
+        for placed in self.layout(scale, text) {
+            let Some(raster) = self.raster(scale, &placed) else {
+                continue;
+            };
+            let left = x + placed.x + raster.left;
+            let top = y + placed.y + raster.top;
+            for (i, v) in raster.coverage.iter().enumerate() {
+                let px = left + (i % raster.width) as i32;
+                let py = top + (i / raster.width) as i32;
+                if *v == 0 || !(0..width).contains(&px) || !(0..height).contains(&py) {
+                    continue;
+                }
+                let v = *v as f32 / 255.0;
+                let pixel = canvas.get_pixel(px as u32, py as u32);
+                let blended = pixel.map2(&color, |p, q| {
+                    (p as f32 * (1.0 - v) + q as f32 * v).clamp(0.0, 255.0) as u8
+                });
+                canvas.draw_pixel(px as u32, py as u32, blended);
+            }
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+
+/// readings older than this are dropped
+const WINDOW: i64 = 7 * 24 * 3600;
+/// an unchanged level is recorded at most this often
+const INTERVAL: i64 = 10 * 60;
+/// smallest rise in percent over the lowest level since the last charge
+/// taken as a charge rather than measurement jitter
+const CHARGE_RISE: usize = 5;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Hash)]
+pub struct Reading {
+    /// unix timestamp in seconds
+    pub time: i64,
+    pub level: usize,
+}
+
+/// rolling battery readings of a device, kept in sled
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct BatteryHistory {
+    pub readings: Vec<Reading>,
+    /// times a charge was first seen
+    pub charges: Vec<i64>,
+}
+
+impl BatteryHistory {
+    pub fn load(db: &utils::database::Db, key: &str) -> BatteryHistory {
+        db.get(key).ok().flatten().unwrap_or_default()
+    }
+
+    pub fn save(&self, db: &utils::database::Db, key: &str) -> anyhow::Result<()> {
+        db.set(key, self)
+    }
+
+    /// whether the last reading is charging, and where the discharge since
+    /// the last charge starts: at the peak the level first dropped from
+    fn scan(&self) -> (bool, usize) {
+        let Some(first) = self.readings.first() else {
+            return (false, 0);
+        };
+        let (mut charging, mut start, mut low) = (false, 0, first.level);
+        for (index, pair) in self.readings.windows(2).enumerate() {
+            let (previous, reading) = (pair[0], pair[1]);
+            if charging {
+                if reading.level < previous.level {
+                    charging = false;
+                    start = index;
+                    low = reading.level;
+                }
+            } else if reading.level >= low + CHARGE_RISE {
+                charging = true;
+            } else {
+                low = low.min(reading.level);
+            }
+        }
+        match charging {
+            true => (true, self.readings.len() - 1),
+            false => (false, start),
+        }
+    }
+
+    /// returns true when the reading starts a charge
+    pub fn record(&mut self, time: i64, level: usize) -> bool {
+        let was_charging = self.charging();
+        let last = self.readings.last().copied();
+        if let Some(last) = last {
+            if last.level == level && time - last.time < INTERVAL {
+                return false;
+            }
+        }
+        self.readings.push(Reading { time, level });
+        self.readings.retain(|x| time - x.time <= WINDOW);
+        self.charges.retain(|x| time - x <= WINDOW);
+        let charged = !was_charging && self.charging();
+        if charged {
+            self.charges.push(time);
+        }
+        charged
+    }
+
+    pub fn level(&self) -> Option<usize> {
+        self.readings.last().map(|x| x.level)
+    }
+
+    /// rose `CHARGE_RISE` over the lowest level since the last charge and
+    /// has not dropped since
+    pub fn charging(&self) -> bool {
+        self.scan().0
+    }
+
+    /// readings since the last charge
+    fn discharging(&self) -> &[Reading] {
+        &self.readings[self.scan().1..]
+    }
+
+    /// percent per hour by least squares, none until an hour of discharge
+    pub fn discharge_rate(&self) -> Option<f64> {
+        let readings = self.discharging();
+        let (first, last) = (readings.first()?, readings.last()?);
+        if last.time - first.time < 3600 {
+            return None;
+        }
+        let n = readings.len() as f64;
+        let hours = |x: &Reading| (x.time - first.time) as f64 / 3600.0;
+        let mean_x = readings.iter().map(hours).sum::<f64>() / n;
+        let mean_y = readings.iter().map(|x| x.level as f64).sum::<f64>() / n;
+        let (mut sxy, mut sxx) = (0.0, 0.0);
+        for reading in readings {
+            let dx = hours(reading) - mean_x;
+            sxy += dx * (reading.level as f64 - mean_y);
+            sxx += dx * dx;
+        }
+        let slope = sxy / sxx;
+        (slope < 0.0).then_some(-slope)
+    }
+
+    pub fn time_to_empty(&self) -> Option<Duration> {
+        let rate = self.discharge_rate()?;
+        let hours = self.level()? as f64 / rate;
+        Some(Duration::seconds((hours * 3600.0) as i64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HALF_HOUR: i64 = 1800;
+
+    /// records `levels` half an hour apart after `history`, returns the
+    /// readings that started a charge
+    fn feed(history: &mut BatteryHistory, levels: &[usize]) -> Vec<usize> {
+        let start = history.readings.last().map_or(0, |x| x.time + HALF_HOUR);
+        levels
+            .iter()
+            .enumerate()
+            .filter(|(index, level)| history.record(start + *index as i64 * HALF_HOUR, **level))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    #[test]
+    fn slow_charge() {
+        let mut history = BatteryHistory::default();
+        feed(&mut history, &[40, 39, 38]);
+        assert!(!history.charging());
+        let started = feed(&mut history, &(39..=60).collect::<Vec<_>>());
+        // 38 + 5
+        assert_eq!(started, [4]);
+        assert!(history.charging());
+        assert_eq!(history.charges.len(), 1);
+        assert_eq!(history.discharge_rate(), None);
+        feed(&mut history, &[59]);
+        assert!(!history.charging());
+    }
+
+    #[test]
+    fn jitter() {
+        let mut history = BatteryHistory::default();
+        let started = feed(
+            &mut history,
+            &[80, 79, 83, 78, 82, 81, 77, 80, 76, 79, 75, 78, 74, 77, 73],
+        );
+        assert!(started.is_empty());
+        assert!(history.charges.is_empty());
+        assert!(!history.charging());
+        assert!(history.discharge_rate().is_some());
+    }
+
+    #[test]
+    fn time_to_empty_after_charge() {
+        let mut history = BatteryHistory::default();
+        // 2% an hour, then a 1% per reading charge to 100
+        feed(&mut history, &(60..=90).rev().collect::<Vec<_>>());
+        feed(&mut history, &(61..=100).collect::<Vec<_>>());
+        assert_eq!(history.charges.len(), 1);
+        // 4% an hour since unplugged
+        feed(
+            &mut history,
+            &(80..=98).rev().step_by(2).collect::<Vec<_>>(),
+        );
+        assert!(!history.charging());
+        let rate = history.discharge_rate().unwrap();
+        assert!((rate - 4.0).abs() < 1e-9, "rate = {}", rate);
+        assert_eq!(history.time_to_empty(), Some(Duration::hours(20)));
+    }
+}
@@ -0,0 +1,109 @@
+// QWeather icons from `<code>.svg` or `<code>.png` files, e.g. the SVGs of
+// https://github.com/qwd/Icons, rasterized once per size as coverage masks
+// so they can be painted in any gray.
+use anyhow::{anyhow, Result};
+use image::{imageops, GrayImage, Luma};
+use imageproc::rect::Rect;
+use log::info;
+use once_cell::sync::{Lazy, OnceCell};
+use resvg::{tiny_skia, usvg};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// icon code, width and height
+type MaskKey = (String, u32, u32);
+
+static ICONS: OnceCell<PathBuf> = OnceCell::new();
+static MASKS: Lazy<Mutex<HashMap<MaskKey, Arc<GrayImage>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn load_icons(path: Option<String>) {
+    if let Some(path) = path {
+        info!("icons: {}", path);
+        ICONS.get_or_init(|| PathBuf::from(path));
+    }
+}
+
+fn find(code: &str) -> Option<PathBuf> {
+    let dir = ICONS.get()?;
+    ["svg", "png"]
+        .iter()
+        .map(|x| dir.join(format!("{}.{}", code, x)))
+        .find(|x| x.is_file())
+}
+
+pub fn has_icon(code: &str) -> bool {
+    find(code).is_some()
+}
+
+/// coverage of the icon scaled to fit `width` x `height`, 255 is ink
+fn rasterize(code: &str, width: u32, height: u32) -> Result<GrayImage> {
+    let path = find(code).ok_or(anyhow!("icon {} not found", code))?;
+    let data = std::fs::read(&path)?;
+    if path.extension().is_some_and(|x| x == "svg") {
+        let tree = usvg::Tree::from_data(&data, &usvg::Options::default())?;
+        let size = tree.size();
+        let scale = (width as f32 / size.width()).min(height as f32 / size.height());
+        let (w, h) = (
+            (size.width() * scale).round().max(1.0) as u32,
+            (size.height() * scale).round().max(1.0) as u32,
+        );
+        let mut pixmap = tiny_skia::Pixmap::new(w, h).ok_or(anyhow!("empty icon"))?;
+        resvg::render(
+            &tree,
+            tiny_skia::Transform::from_scale(scale, scale),
+            &mut pixmap.as_mut(),
+        );
+        let alpha = pixmap.pixels().iter().map(|x| x.alpha()).collect();
+        GrayImage::from_raw(w, h, alpha).ok_or(anyhow!("bad icon size"))
+    } else {
+        let img = image::load_from_memory(&data)?;
+        let scale = (width as f32 / img.width() as f32).min(height as f32 / img.height() as f32);
+        let (w, h) = (
+            (img.width() as f32 * scale).round().max(1.0) as u32,
+            (img.height() as f32 * scale).round().max(1.0) as u32,
+        );
+        let img = img.resize_exact(w, h, imageops::FilterType::Lanczos3);
+        // transparent icons carry their shape in alpha, opaque ones are dark
+        // on light
+        Ok(match img.color().has_alpha() {
+            true => {
+                let img = img.to_luma_alpha8();
+                GrayImage::from_fn(w, h, |x, y| Luma([img.get_pixel(x, y).0[1]]))
+            }
+            false => {
+                let mut img = img.to_luma8();
+                imageops::invert(&mut img);
+                img
+            }
+        })
+    }
+}
+
+fn mask(code: &str, width: u32, height: u32) -> Result<Arc<GrayImage>> {
+    let key = (code.to_string(), width, height);
+    if let Some(mask) = MASKS.lock().unwrap().get(&key) {
+        return Ok(mask.clone());
+    }
+    let mask = Arc::new(rasterize(code, width, height)?);
+    MASKS.lock().unwrap().insert(key, mask.clone());
+    Ok(mask)
+}
+
+/// paint the icon centered in `rect`
+pub fn draw_icon(canvas: &mut GrayImage, rect: Rect, code: &str, color: Luma<u8>) -> Result<()> {
+    let mask = mask(code, rect.width(), rect.height())?;
+    let left = rect.left() + (rect.width() - mask.width()) as i32 / 2;
+    let top = rect.top() + (rect.height() - mask.height()) as i32 / 2;
+    for (x, y, coverage) in mask.enumerate_pixels() {
+        let (cx, cy) = (left + x as i32, top + y as i32);
+        if cx < 0 || cy < 0 || cx >= canvas.width() as i32 || cy >= canvas.height() as i32 {
+            continue;
+        }
+        let alpha = coverage.0[0] as u32;
+        let pixel = canvas.get_pixel_mut(cx as u32, cy as u32);
+        pixel.0[0] = ((pixel.0[0] as u32 * (255 - alpha) + color.0[0] as u32 * alpha) / 255) as u8;
+    }
+    Ok(())
+}
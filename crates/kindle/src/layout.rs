@@ -0,0 +1,360 @@
+use super::lunar;
+use super::quote::quote_of;
+use super::shared::*;
+use super::widgets::{agenda_text, holiday_text, upcoming, QrWidget};
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Timelike};
+use image::{GrayImage, Luma};
+use imageproc::{drawing, rect::Rect};
+use log::{error, info, warn};
+use once_cell::sync::OnceCell;
+use rusttype::Scale;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(Deserialize, Debug)]
+pub struct Layout {
+    /// size of the design, scaled to the canvas of the device profile
+    #[serde(default = "default_width")]
+    pub width: u32,
+    #[serde(default = "default_height")]
+    pub height: u32,
+    #[serde(default = "default_background")]
+    pub background: u8,
+    #[serde(default)]
+    pub elements: Vec<Element>,
+}
+
+fn default_width() -> u32 {
+    600
+}
+
+fn default_height() -> u32 {
+    800
+}
+
+fn default_background() -> u8 {
+    255
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Element {
+    Text(TextElement),
+    Rect(RectElement),
+    Qr(QrElement),
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TextElement {
+    pub x: i32,
+    pub y: i32,
+    #[serde(default = "default_align")]
+    pub align: AlignHorizontal,
+    #[serde(default = "default_valign")]
+    pub valign: AlignVertical,
+    #[serde(default = "default_font")]
+    pub font: String,
+    pub scale: f32,
+    #[serde(default)]
+    pub color: u8,
+    /// literal text, used when `bind` is absent
+    pub text: Option<String>,
+    /// data binding, e.g. `now.day`, `battery`, `weather.3d[0].text`, `quote.text`
+    pub bind: Option<String>,
+    /// template for the bound value, `{}` is replaced by the value
+    pub format: Option<String>,
+    /// center on the ink bounds instead of the font metrics
+    #[serde(default)]
+    pub tight: bool,
+    /// wrap into a box this wide, shrinking down to `min_scale` before
+    /// cutting the text with an ellipsis
+    pub width: Option<u32>,
+    /// height of the box, `max_lines` lines at `scale` if absent
+    pub height: Option<u32>,
+    #[serde(default = "default_max_lines")]
+    pub max_lines: usize,
+    pub min_scale: Option<f32>,
+}
+
+fn default_max_lines() -> usize {
+    1
+}
+
+/// distance between baselines of wrapped text relative to the scale
+const LINE_SPACING: f32 = 1.2;
+
+fn default_align() -> AlignHorizontal {
+    AlignHorizontal::Left
+}
+
+fn default_valign() -> AlignVertical {
+    AlignVertical::Top
+}
+
+fn default_font() -> String {
+    "main".to_string()
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RectElement {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub color: u8,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct QrElement {
+    pub x: i32,
+    pub y: i32,
+    /// side of the square the code is centered in
+    pub size: u32,
+    /// payload, `kindle.qr` if absent
+    pub text: Option<String>,
+}
+
+static LAYOUTS: OnceCell<HashMap<String, Layout>> = OnceCell::new();
+
+fn parse_layout(path: &Path) -> Result<Layout> {
+    let raw = std::fs::read_to_string(path)?;
+    match path.extension().and_then(|x| x.to_str()) {
+        Some("toml") => Ok(toml::from_str(&raw)?),
+        Some("json") => Ok(serde_json::from_str(&raw)?),
+        _ => Err(anyhow!("unsupported layout format")),
+    }
+}
+
+pub fn load_layouts(path: Option<String>) {
+    LAYOUTS.get_or_init(|| {
+        let mut map = HashMap::new();
+        let Some(path) = path else {
+            return map;
+        };
+        let entries = match std::fs::read_dir(&path) {
+            Ok(entries) => entries,
+            Err(err) => {
+                warn!("cannot read layouts from {}: {}", path, err);
+                return map;
+            }
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(name) = path.file_stem().and_then(|x| x.to_str()) else {
+                continue;
+            };
+            match parse_layout(&path) {
+                Ok(layout) => {
+                    info!("loading layout {}: {:?}", name, path);
+                    map.insert(name.to_string(), layout);
+                }
+                Err(err) => error!("failed to load layout {:?}: {}", path, err),
+            }
+        }
+        map
+    });
+}
+
+pub fn get_layout(name: &str) -> Option<&'static Layout> {
+    LAYOUTS.get().and_then(|x| x.get(name))
+}
+
+pub fn list_layouts() -> Vec<&'static str> {
+    LAYOUTS
+        .get()
+        .map(|x| x.keys().map(|x| x.as_str()).collect())
+        .unwrap_or_default()
+}
+
+/// split `weather.3d[0].text` into `["weather", "3d", "0", "text"]`
+fn split_path(path: &str) -> Vec<&str> {
+    path.split(['.', '[', ']'])
+        .filter(|x| !x.is_empty())
+        .collect()
+}
+
+pub fn resolve(path: &str, context: &Context) -> Option<String> {
+    let segments = split_path(path);
+    match segments.as_slice() {
+        ["battery"] => context.battery.map(|x| x.to_string()),
+        ["now", field] => {
+            let now = context.now?;
+            Some(match *field {
+                "year" => now.year().to_string(),
+                "month" => now.month().to_string(),
+                "day" => now.format("%d").to_string(),
+                "hour" => now.format("%H").to_string(),
+                "minute" => now.format("%M").to_string(),
+                "second" => format!("{:02}", now.second()),
+                "weekday" => context.locale.weekday_long(now.weekday()).to_string(),
+                "date" => now.format("%Y-%m-%d").to_string(),
+                "time" => now.format("%H:%M:%S").to_string(),
+                "datetime" => now.format("%Y-%m-%d %H:%M:%S").to_string(),
+                _ => return None,
+            })
+        }
+        ["lunar", field] => {
+            let date = context.now?.date_naive();
+            let lunar = lunar::lunar_date(date);
+            Some(match *field {
+                "year" => lunar.year_name(),
+                "zodiac" => lunar.zodiac().to_string(),
+                "month" => lunar.month_name(),
+                "day" => lunar.day_name(),
+                "term" => lunar::solar_term(date)?.to_string(),
+                "festival" => lunar::festival(date)?.to_string(),
+                _ => return None,
+            })
+        }
+        ["holiday", field] => {
+            let day = context.holiday.as_ref()?;
+            Some(match *field {
+                "name" => day.name.clone()?,
+                "text" => holiday_text(day, context.locale)?,
+                "workday" => day.is_workday().to_string(),
+                _ => return None,
+            })
+        }
+        ["agenda", index, field] => {
+            let index: usize = index.parse().ok()?;
+            let event = *upcoming(context).get(index)?;
+            Some(match *field {
+                "summary" => event.summary.clone(),
+                "location" => event.location.clone()?,
+                "time" => context.local(event.start).format("%H:%M").to_string(),
+                "text" => agenda_text(event, context),
+                _ => return None,
+            })
+        }
+        ["quote", field] => {
+            let quote = quote_of(context.now?.date_naive())?;
+            match *field {
+                "text" => Some(quote.text),
+                "author" => quote.author,
+                _ => None,
+            }
+        }
+        ["weather", "24h", field] => {
+            let forecast = context.forecast_24h.as_ref()?;
+            Some(match *field {
+                "min" => forecast.min_temp.to_string(),
+                "max" => forecast.max_temp.to_string(),
+                "text" => forecast.texts.first()?.clone(),
+                _ => return None,
+            })
+        }
+        ["weather", "3d", index, field] => {
+            let index: usize = index.parse().ok()?;
+            let forecast = context.forecast_3d.as_ref()?.get(index)?;
+            Some(match *field {
+                "text" => forecast.text.clone(),
+                "min" => forecast.temp_min.to_string(),
+                "max" => forecast.temp_max.to_string(),
+                "icon" => forecast.icon.clone(),
+                "date" => forecast.date.format("%m-%d").to_string(),
+                _ => return None,
+            })
+        }
+        _ => None,
+    }
+}
+
+fn text_of(element: &TextElement, context: &Context) -> Option<String> {
+    match &element.bind {
+        Some(bind) => {
+            let value = resolve(bind, context)?;
+            Some(match &element.format {
+                Some(format) => format.replace("{}", &value),
+                None => value,
+            })
+        }
+        None => element.text.clone(),
+    }
+}
+
+pub fn generate(img: &mut GrayImage, layout: &'static Layout, context: &Context) -> Result<()> {
+    let frame = Frame::new(img, layout.width, layout.height);
+
+    let rect = Rect::at(0, 0).of_size(img.width(), img.height());
+    drawing::draw_filled_rect_mut(img, rect, Luma([layout.background]));
+
+    for element in layout.elements.iter() {
+        match element {
+            Element::Rect(element) => {
+                let rect = frame.rect(element.x, element.y, element.width, element.height);
+                drawing::draw_filled_rect_mut(img, rect, Luma([element.color]));
+            }
+            Element::Qr(element) => {
+                let rect = frame.rect(element.x, element.y, element.size, element.size);
+                let widget = QrWidget {
+                    payload: element.text.as_deref(),
+                };
+                widget.render(img, rect, context)?;
+            }
+            Element::Text(element) => {
+                let Some(text) = text_of(element, context) else {
+                    continue;
+                };
+                if text.is_empty() {
+                    continue;
+                }
+                let font =
+                    get_font(&element.font).ok_or(anyhow!("{} font not found", element.font))?;
+                let base = (frame.x(element.x), frame.y(element.y));
+                let scale = Scale::uniform(frame.scale(element.scale));
+                let color = Luma([element.color]);
+                if let Some(width) = element.width {
+                    let scale = frame.scale(element.scale);
+                    let lines = element.max_lines.max(1) as f32;
+                    let height = match element.height {
+                        Some(height) => frame.scale(height as f32),
+                        None => scale * (1.0 + LINE_SPACING * (lines - 1.0)),
+                    };
+                    let width = (width as f32 * frame.scale(1.0)).round() as u32;
+                    let height = height.round() as u32;
+                    let left = match element.align {
+                        AlignHorizontal::Left => base.0,
+                        AlignHorizontal::Center => base.0 - width as i32 / 2,
+                        AlignHorizontal::Right => base.0 - width as i32,
+                    };
+                    let top = match element.valign {
+                        AlignVertical::Top => base.1,
+                        AlignVertical::Center => base.1 - height as i32 / 2,
+                        AlignVertical::Bottom => base.1 - height as i32,
+                    };
+                    let style = ParagraphStyle {
+                        font: &element.font,
+                        color,
+                        align: (element.align, element.valign),
+                        max_scale: Some(scale),
+                        min_scale: frame.scale(element.min_scale.unwrap_or(element.scale / 2.0)),
+                        max_lines: element.max_lines,
+                        line_spacing: LINE_SPACING,
+                    };
+                    style.draw(
+                        img,
+                        Rect::at(left, top).of_size(width.max(1), height.max(1)),
+                        &text,
+                    )?;
+                } else if element.tight {
+                    draw_centered_text(img, color, base, scale, font, &text);
+                } else {
+                    draw_aligned_text(
+                        img,
+                        color,
+                        base,
+                        scale,
+                        font,
+                        &text,
+                        (element.align, element.valign),
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -1,31 +1,108 @@
 mod alpha;
 mod bravo;
+mod cache;
 mod charlie;
+mod delta;
+mod device;
+mod dither;
+mod echo;
+mod encode;
+mod font;
+mod history;
+mod icon;
+mod layout;
+mod locale;
+pub mod lunar;
+mod night;
+mod photo;
+mod profile;
+mod qr;
+mod quote;
+mod refresh;
+mod schedule;
 mod shared;
+mod template;
+mod widgets;
 
 use anyhow::{anyhow, Result};
+use chrono::Weekday;
 use image::GrayImage;
 use log::info;
 use once_cell::sync::OnceCell;
-use rand::Rng;
+use rand::seq::SliceRandom;
+pub use cache::{get_render, put_render, RenderKey};
+pub use device::{
+    configured_device, get_device, list_devices, load_devices, save_device, Device, DEFAULT_DEVICE,
+};
+pub use dither::{quantize, Dither};
+pub use encode::{encode, Format};
+pub use history::{BatteryHistory, Reading};
+pub use icon::load_icons;
+pub use layout::load_layouts;
+pub use locale::{default_locale, set_default_locale, Locale};
+pub use photo::{set_photos, Photos};
+pub use profile::{get_profile, load_profiles, Profile};
+pub use qr::{qr_image, qr_payload, set_qr, Qr};
+pub use quote::{add_quote, quote_of, set_quotes, Quote};
+pub use refresh::{next_refresh, set_refresh, Refresh};
+pub use schedule::{set_schedule, Slot};
 pub use shared::{load_fonts, Context};
+pub use template::load_templates;
 
-static DEFAULT_STYLE: OnceCell<Option<usize>> = OnceCell::new();
+static DEFAULT_STYLE: OnceCell<Option<String>> = OnceCell::new();
 
-pub fn set_default_style(style: Option<usize>) {
+const BUILTIN_STYLES: [&str; 5] = ["alpha", "bravo", "charlie", "delta", "echo"];
+
+pub fn set_default_style(style: Option<String>) {
     if let Some(style) = DEFAULT_STYLE.get_or_init(|| style) {
         info!("kindle's default style={}", style);
     }
 }
 
-pub async fn factory(style: Option<usize>, context: &Context) -> Result<GrayImage> {
-    let n = style
-        .or_else(|| *DEFAULT_STYLE.get().unwrap())
-        .unwrap_or_else(|| rand::thread_rng().gen_range(0..3));
-    return match n {
-        0 => alpha::generate(context).await,
-        1 => bravo::generate(context).await,
-        2 => charlie::generate(context).await,
-        _ => Err(anyhow!("unknown style = {}", n)),
-    };
+static WEEK_START: OnceCell<Weekday> = OnceCell::new();
+
+pub fn set_week_start(week_start: Weekday) {
+    info!("kindle's week starts on {}", week_start);
+    WEEK_START.get_or_init(|| week_start);
+}
+
+/// first column of month grids, monday unless configured
+fn week_start() -> Weekday {
+    WEEK_START.get().copied().unwrap_or(Weekday::Mon)
+}
+
+pub fn list_styles() -> Vec<&'static str> {
+    let mut styles = BUILTIN_STYLES.to_vec();
+    styles.extend(layout::list_layouts());
+    styles.extend(template::list_templates());
+    styles
+}
+
+pub async fn factory(style: Option<&str>, context: &Context) -> Result<GrayImage> {
+    let name = style
+        .or_else(|| DEFAULT_STYLE.get().unwrap().as_deref())
+        .or_else(|| schedule::scheduled_style(context))
+        .map(|x| x.to_string())
+        .unwrap_or_else(|| {
+            list_styles()
+                .choose(&mut rand::thread_rng())
+                .unwrap()
+                .to_string()
+        });
+    let mut img = context.profile.canvas();
+    match name.as_str() {
+        "0" | "alpha" => alpha::generate(&mut img, context)?,
+        "1" | "bravo" => bravo::generate(&mut img, context)?,
+        "2" | "charlie" => charlie::generate(&mut img, context)?,
+        "3" | "delta" => delta::generate(&mut img, context)?,
+        "4" | "echo" => echo::generate(&mut img, context)?,
+        "night" => night::generate(&mut img, context)?,
+        "photo" => photo::generate(&mut img, context)?,
+        name => match (layout::get_layout(name), template::get_template(name)) {
+            (Some(layout), _) => layout::generate(&mut img, layout, context)?,
+            (None, Some(template)) => template::generate(&mut img, template, context)?,
+            (None, None) => return Err(anyhow!("unknown style = {}", name)),
+        },
+    }
+    Ok(img)
 }
@@ -0,0 +1,242 @@
+// Weekday names, numerals and labels of the screens in zh-CN, en and ja,
+// chosen by `?locale=`, the device or `kindle.locale`. Lunar dates and
+// holiday names stay chinese, they only exist in the chinese calendar.
+use anyhow::anyhow;
+use chrono::{Datelike, NaiveDate, Weekday};
+use log::info;
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+use std::str::FromStr;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(try_from = "String")]
+pub enum Locale {
+    #[default]
+    ZhCn,
+    En,
+    Ja,
+}
+
+impl FromStr for Locale {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('_', "-").as_str() {
+            "zh" | "zh-cn" | "zh-hans" => Ok(Locale::ZhCn),
+            "en" | "en-us" | "en-gb" => Ok(Locale::En),
+            "ja" | "ja-jp" => Ok(Locale::Ja),
+            _ => Err(anyhow!("unknown locale = {}", s)),
+        }
+    }
+}
+
+impl TryFrom<String> for Locale {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse().map_err(|e: anyhow::Error| e.to_string())
+    }
+}
+
+static DEFAULT_LOCALE: OnceCell<Locale> = OnceCell::new();
+
+pub fn set_default_locale(locale: Locale) {
+    info!("kindle's default locale is {:?}", locale);
+    DEFAULT_LOCALE.get_or_init(|| locale);
+}
+
+/// `kindle.locale`, zh-CN unless configured
+pub fn default_locale() -> Locale {
+    DEFAULT_LOCALE.get().copied().unwrap_or_default()
+}
+
+const WEEKDAY_LONG_ZH: [&str; 7] = ["周一", "周二", "周三", "周四", "周五", "周六", "周日"];
+const WEEKDAY_SHORT_ZH: [&str; 7] = ["一", "二", "三", "四", "五", "六", "日"];
+const WEEKDAY_LONG_EN: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+const WEEKDAY_SHORT_EN: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+const WEEKDAY_LONG_JA: [&str; 7] = [
+    "月曜日",
+    "火曜日",
+    "水曜日",
+    "木曜日",
+    "金曜日",
+    "土曜日",
+    "日曜日",
+];
+const WEEKDAY_SHORT_JA: [&str; 7] = ["月", "火", "水", "木", "金", "土", "日"];
+const MONTHS_EN: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const NUMERALS: [&str; 11] = [
+    "〇", "一", "二", "三", "四", "五", "六", "七", "八", "九", "十",
+];
+
+/// how the day of month is written
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Numerals {
+    /// 01, 21
+    Arabic,
+    /// 五, 十, 二十一
+    Chinese,
+    /// 〇五, 一十, 廿一, 卅一 of traditional calendars
+    ChineseTraditional,
+}
+
+fn tens(day: u32) -> &'static str {
+    match day / 10 {
+        1 => "十",
+        2 => "廿",
+        3 => "卅",
+        _ => NUMERALS[0],
+    }
+}
+
+impl Numerals {
+    pub fn day(self, day: u32) -> String {
+        match self {
+            Numerals::Arabic => format!("{:02}", day),
+            Numerals::Chinese => match (day / 10, day % 10) {
+                (0, ones) => NUMERALS[ones as usize].to_string(),
+                (1, 0) => NUMERALS[10].to_string(),
+                (1, ones) => format!("{}{}", NUMERALS[10], NUMERALS[ones as usize]),
+                (tens, 0) => format!("{}{}", NUMERALS[tens as usize], NUMERALS[10]),
+                (tens, ones) => format!(
+                    "{}{}{}",
+                    NUMERALS[tens as usize], NUMERALS[10], NUMERALS[ones as usize]
+                ),
+            },
+            Numerals::ChineseTraditional => {
+                let (first, second) = stacked_day(day);
+                match day < 10 {
+                    true => format!("{}{}", NUMERALS[0], first),
+                    false => format!("{}{}", first, second),
+                }
+            }
+        }
+    }
+}
+
+/// 五号, 一十, 十一, 廿一, 卅一, always two characters
+pub fn stacked_day(day: u32) -> (&'static str, &'static str) {
+    if day.is_multiple_of(10) {
+        (NUMERALS[(day / 10) as usize], NUMERALS[10])
+    } else if day < 10 {
+        (NUMERALS[day as usize], "号")
+    } else {
+        (tens(day), NUMERALS[(day % 10) as usize])
+    }
+}
+
+impl Locale {
+    pub fn weekday_long(self, weekday: Weekday) -> &'static str {
+        let names = match self {
+            Locale::ZhCn => WEEKDAY_LONG_ZH,
+            Locale::En => WEEKDAY_LONG_EN,
+            Locale::Ja => WEEKDAY_LONG_JA,
+        };
+        names[weekday.num_days_from_monday() as usize]
+    }
+
+    pub fn weekday_short(self, weekday: Weekday) -> &'static str {
+        let names = match self {
+            Locale::ZhCn => WEEKDAY_SHORT_ZH,
+            Locale::En => WEEKDAY_SHORT_EN,
+            Locale::Ja => WEEKDAY_SHORT_JA,
+        };
+        names[weekday.num_days_from_monday() as usize]
+    }
+
+    /// numerals of a day written out in words
+    pub fn numerals(self) -> Numerals {
+        match self {
+            Locale::ZhCn => Numerals::ChineseTraditional,
+            Locale::En => Numerals::Arabic,
+            Locale::Ja => Numerals::Chinese,
+        }
+    }
+
+    /// 2024年10月, October 2024
+    pub fn month_title(self, date: NaiveDate) -> String {
+        match self {
+            Locale::ZhCn | Locale::Ja => format!("{}年{}月", date.year(), date.month()),
+            Locale::En => format!("{} {}", MONTHS_EN[date.month0() as usize], date.year()),
+        }
+    }
+
+    /// padded to two digits so the label keeps its width on a low battery
+    pub fn battery(self, level: usize) -> String {
+        match self {
+            Locale::ZhCn => format!("电量：{:02}%", level),
+            Locale::En => format!("Battery: {:02}%", level),
+            Locale::Ja => format!("電池：{:02}%", level),
+        }
+    }
+
+    /// prefix of the update time
+    pub fn updated(self) -> &'static str {
+        match self {
+            Locale::ZhCn | Locale::Ja => "更新：",
+            Locale::En => "Update: ",
+        }
+    }
+
+    pub fn charging(self) -> &'static str {
+        match self {
+            Locale::ZhCn => "充电中",
+            Locale::En => "Charging",
+            Locale::Ja => "充電中",
+        }
+    }
+
+    /// time until the battery runs out
+    pub fn hours_left(self, hours: i64) -> String {
+        match self {
+            Locale::ZhCn => format!("约 {} 小时", hours),
+            Locale::En => format!("~{} hours", hours),
+            Locale::Ja => format!("約 {} 時間", hours),
+        }
+    }
+
+    pub fn all_day(self) -> &'static str {
+        match self {
+            Locale::ZhCn => "全天",
+            Locale::En => "All day",
+            Locale::Ja => "終日",
+        }
+    }
+
+    /// a statutory holiday, such as 休 · 国庆节
+    pub fn holiday(self, name: &str) -> String {
+        match self {
+            Locale::ZhCn | Locale::Ja => format!("休 · {}", name),
+            Locale::En => format!("Off · {}", name),
+        }
+    }
+
+    /// a weekend made a workday for `name`, such as 班 · 国庆节调休
+    pub fn adjusted(self, name: &str) -> String {
+        match self {
+            Locale::ZhCn => format!("班 · {}调休", name),
+            Locale::En => format!("Workday · {}", name),
+            Locale::Ja => format!("出勤 · {}振替", name),
+        }
+    }
+}
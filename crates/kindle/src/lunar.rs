@@ -0,0 +1,318 @@
+// Chinese lunisolar calendar computed from approximate positions of the sun
+// and the moon (Meeus, Astronomical Algorithms, ch. 25 and 49) instead of a
+// lookup table, days are counted in Beijing time.
+use chrono::{Datelike, NaiveDate};
+use std::f64::consts::PI;
+
+const STEMS: [&str; 10] = ["甲", "乙", "丙", "丁", "戊", "己", "庚", "辛", "壬", "癸"];
+const BRANCHES: [&str; 12] = [
+    "子", "丑", "寅", "卯", "辰", "巳", "午", "未", "申", "酉", "戌", "亥",
+];
+const ZODIAC: [&str; 12] = [
+    "鼠", "牛", "虎", "兔", "龙", "蛇", "马", "羊", "猴", "鸡", "狗", "猪",
+];
+const MONTHS: [&str; 12] = [
+    "正", "二", "三", "四", "五", "六", "七", "八", "九", "十", "冬", "腊",
+];
+const DIGITS: [&str; 11] = [
+    "", "一", "二", "三", "四", "五", "六", "七", "八", "九", "十",
+];
+
+/// indexed by solar longitude / 15°
+const SOLAR_TERMS: [&str; 24] = [
+    "春分", "清明", "谷雨", "立夏", "小满", "芒种", "夏至", "小暑", "大暑", "立秋", "处暑", "白露",
+    "秋分", "寒露", "霜降", "立冬", "小雪", "大雪", "冬至", "小寒", "大寒", "立春", "雨水", "惊蛰",
+];
+
+const LUNAR_FESTIVALS: [(u32, u32, &str); 9] = [
+    (1, 1, "春节"),
+    (1, 15, "元宵"),
+    (2, 2, "龙抬头"),
+    (5, 5, "端午"),
+    (7, 7, "七夕"),
+    (7, 15, "中元"),
+    (8, 15, "中秋"),
+    (9, 9, "重阳"),
+    (12, 8, "腊八"),
+];
+
+const SOLAR_FESTIVALS: [(u32, u32, &str); 10] = [
+    (1, 1, "元旦"),
+    (3, 8, "妇女节"),
+    (3, 12, "植树节"),
+    (5, 1, "劳动节"),
+    (5, 4, "青年节"),
+    (6, 1, "儿童节"),
+    (7, 1, "建党节"),
+    (8, 1, "建军节"),
+    (9, 10, "教师节"),
+    (10, 1, "国庆节"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LunarDate {
+    /// gregorian year in which the lunar year starts
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+    pub leap: bool,
+}
+
+impl LunarDate {
+    /// 甲辰
+    pub fn year_name(&self) -> String {
+        let index = (self.year - 4).rem_euclid(60) as usize;
+        format!("{}{}", STEMS[index % 10], BRANCHES[index % 12])
+    }
+
+    /// 龙
+    pub fn zodiac(&self) -> &'static str {
+        ZODIAC[(self.year - 4).rem_euclid(12) as usize]
+    }
+
+    /// 闰二月
+    pub fn month_name(&self) -> String {
+        format!(
+            "{}{}月",
+            if self.leap { "闰" } else { "" },
+            MONTHS[self.month as usize - 1]
+        )
+    }
+
+    /// 初一, 十五, 廿三, 三十
+    pub fn day_name(&self) -> String {
+        match self.day {
+            1..=10 => format!("初{}", DIGITS[self.day as usize]),
+            11..=19 => format!("十{}", DIGITS[self.day as usize - 10]),
+            20 => "二十".to_string(),
+            21..=29 => format!("廿{}", DIGITS[self.day as usize - 20]),
+            _ => "三十".to_string(),
+        }
+    }
+}
+
+fn julian_day_number(date: NaiveDate) -> i64 {
+    date.num_days_from_ce() as i64 + 1721425
+}
+
+fn delta_t_days(year: i32) -> f64 {
+    let t = (year - 2000) as f64;
+    (62.92 + 0.32217 * t + 0.005589 * t * t) / 86400.0
+}
+
+/// dynamical time of 00:00 in Beijing of the given julian day number
+fn beijing_midnight(day: i64, year: i32) -> f64 {
+    day as f64 - 0.5 - 8.0 / 24.0 + delta_t_days(year)
+}
+
+/// julian day number of the Beijing date of a dynamical time
+fn beijing_day(jde: f64, year: i32) -> i64 {
+    (jde - delta_t_days(year) + 8.0 / 24.0 + 0.5).floor() as i64
+}
+
+fn sin(degree: f64) -> f64 {
+    (degree * PI / 180.0).sin()
+}
+
+/// apparent geocentric longitude of the sun in degrees, accurate to 0.01°
+fn solar_longitude(jde: f64) -> f64 {
+    let t = (jde - 2451545.0) / 36525.0;
+    let l0 = 280.46646 + 36000.76983 * t + 0.0003032 * t * t;
+    let m = 357.52911 + 35999.05029 * t - 0.0001537 * t * t;
+    let c = (1.914602 - 0.004817 * t - 0.000014 * t * t) * sin(m)
+        + (0.019993 - 0.000101 * t) * sin(2.0 * m)
+        + 0.000289 * sin(3.0 * m);
+    let omega = 125.04 - 1934.136 * t;
+    (l0 + c - 0.00569 - 0.00478 * sin(omega)).rem_euclid(360.0)
+}
+
+/// dynamical time of the k-th new moon since 2000-01-06
+fn new_moon(k: i64) -> f64 {
+    let k = k as f64;
+    let t = k / 1236.85;
+    let (t2, t3, t4) = (t * t, t * t * t, t * t * t * t);
+    let jde =
+        2451550.09766 + 29.530588861 * k + 0.00015437 * t2 - 0.000000150 * t3 + 0.00000000073 * t4;
+    let e = 1.0 - 0.002516 * t - 0.0000074 * t2;
+    let m = 2.5534 + 29.10535670 * k - 0.0000014 * t2 - 0.00000011 * t3;
+    let mp = 201.5643 + 385.81693528 * k + 0.0107582 * t2 + 0.00001238 * t3 - 0.000000058 * t4;
+    let f = 160.7108 + 390.67050284 * k - 0.0016118 * t2 - 0.00000227 * t3 + 0.000000011 * t4;
+    let omega = 124.7746 - 1.56375588 * k + 0.0020672 * t2 + 0.00000215 * t3;
+    let correction = -0.40720 * sin(mp)
+        + 0.17241 * e * sin(m)
+        + 0.01608 * sin(2.0 * mp)
+        + 0.01039 * sin(2.0 * f)
+        + 0.00739 * e * sin(mp - m)
+        - 0.00514 * e * sin(mp + m)
+        + 0.00208 * e * e * sin(2.0 * m)
+        - 0.00111 * sin(mp - 2.0 * f)
+        - 0.00057 * sin(mp + 2.0 * f)
+        + 0.00056 * e * sin(2.0 * mp + m)
+        - 0.00042 * sin(3.0 * mp)
+        + 0.00042 * e * sin(m + 2.0 * f)
+        + 0.00038 * e * sin(m - 2.0 * f)
+        - 0.00024 * e * sin(2.0 * mp - m)
+        - 0.00017 * sin(omega)
+        - 0.00007 * sin(mp + 2.0 * m)
+        + 0.00004 * sin(2.0 * mp - 2.0 * f)
+        + 0.00004 * sin(3.0 * m)
+        + 0.00003 * sin(mp + m - 2.0 * f)
+        + 0.00003 * sin(2.0 * mp + 2.0 * f)
+        - 0.00003 * sin(mp + m + 2.0 * f)
+        + 0.00003 * sin(mp - m + 2.0 * f)
+        - 0.00002 * sin(mp - m - 2.0 * f)
+        - 0.00002 * sin(3.0 * mp + m)
+        + 0.00002 * sin(4.0 * mp);
+    jde + correction
+}
+
+fn new_moon_day(k: i64, year: i32) -> i64 {
+    beijing_day(new_moon(k), year)
+}
+
+/// the new moon which starts the lunar month containing `day`
+fn month_on_or_before(day: i64, year: i32) -> i64 {
+    let mut k = ((day as f64 - 2451550.1) / 29.530588861).floor() as i64 + 1;
+    while new_moon_day(k, year) > day {
+        k -= 1;
+    }
+    k
+}
+
+/// number of solar longitude boundaries of `step` degrees crossed during the
+/// days `[start, end)`
+fn crossings(start: i64, end: i64, step: f64, year: i32) -> i64 {
+    let from = solar_longitude(beijing_midnight(start, year));
+    let delta = (solar_longitude(beijing_midnight(end, year)) - from).rem_euclid(360.0);
+    ((from + delta) / step).floor() as i64 - (from / step).floor() as i64
+}
+
+fn winter_solstice_day(year: i32) -> i64 {
+    let dec_18 = julian_day_number(NaiveDate::from_ymd_opt(year, 12, 18).unwrap());
+    (dec_18..dec_18 + 7)
+        .find(|&day| crossings(day, day + 1, 90.0, year) == 1)
+        .unwrap_or(dec_18 + 3)
+}
+
+/// matches the published calendar within 1900 to 2100, where the error of
+/// the approximate new moons and delta t stays well under the margins
+pub fn lunar_date(date: NaiveDate) -> LunarDate {
+    let day = julian_day_number(date);
+    let mut year = date.year();
+    if day < winter_solstice_day(year) {
+        year -= 1;
+    }
+    loop {
+        // a sui runs from the month of one winter solstice to the next
+        let first = month_on_or_before(winter_solstice_day(year), year);
+        let last = month_on_or_before(winter_solstice_day(year + 1), year + 1);
+        if day >= new_moon_day(last, year + 1) {
+            year += 1;
+            continue;
+        }
+        let leap_sui = last - first == 13;
+        let mut leap_found = false;
+        let mut month = 11;
+        let mut lunar_year = year;
+        let (mut start, mut leap) = (new_moon_day(first, year), false);
+        for k in first..last {
+            start = new_moon_day(k, year);
+            let end = new_moon_day(k + 1, year);
+            leap = leap_sui && !leap_found && crossings(start, end, 30.0, year) == 0;
+            if leap {
+                leap_found = true;
+            } else if k != first {
+                month = month % 12 + 1;
+                if month == 1 {
+                    lunar_year = year + 1;
+                }
+            }
+            if day < end {
+                break;
+            }
+        }
+        // the sui always ends after the day, else it stays in the last month
+        return LunarDate {
+            year: lunar_year,
+            month,
+            day: (day - start + 1) as u32,
+            leap,
+        };
+    }
+}
+
+pub fn solar_term(date: NaiveDate) -> Option<&'static str> {
+    let day = julian_day_number(date);
+    if crossings(day, day + 1, 15.0, date.year()) == 0 {
+        return None;
+    }
+    let longitude = solar_longitude(beijing_midnight(day + 1, date.year()));
+    Some(SOLAR_TERMS[(longitude / 15.0).floor() as usize % 24])
+}
+
+pub fn festival(date: NaiveDate) -> Option<&'static str> {
+    let lunar = lunar_date(date);
+    if !lunar.leap {
+        if let Some(name) = LUNAR_FESTIVALS
+            .iter()
+            .find(|(month, day, _)| *month == lunar.month && *day == lunar.day)
+        {
+            return Some(name.2);
+        }
+        if lunar.month == 12 {
+            let tomorrow = lunar_date(date.succ_opt()?);
+            if tomorrow.month == 1 && tomorrow.day == 1 {
+                return Some("除夕");
+            }
+        }
+    }
+    SOLAR_FESTIVALS
+        .iter()
+        .find(|(month, day, _)| *month == date.month() && *day == date.day())
+        .map(|x| x.2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn name(year: i32, month: u32, day: u32) -> String {
+        let lunar = lunar_date(date(year, month, day));
+        format!(
+            "{}{}{}",
+            lunar.year_name(),
+            lunar.month_name(),
+            lunar.day_name()
+        )
+    }
+
+    #[test]
+    fn new_year() {
+        assert_eq!(name(2024, 2, 10), "甲辰正月初一");
+        assert_eq!(festival(date(2024, 2, 10)), Some("春节"));
+        assert_eq!(name(2024, 2, 9), "癸卯腊月三十");
+        assert_eq!(festival(date(2024, 2, 9)), Some("除夕"));
+    }
+
+    #[test]
+    fn leap_months() {
+        assert_eq!(name(2023, 3, 22), "癸卯闰二月初一");
+        assert_eq!(name(2025, 7, 25), "乙巳闰六月初一");
+        assert_eq!(name(2033, 12, 22), "癸丑闰冬月初一");
+    }
+
+    #[test]
+    fn new_moon_near_midnight() {
+        assert_eq!(name(2057, 9, 28), "丁丑九月初一");
+    }
+
+    #[test]
+    fn solar_terms() {
+        assert_eq!(solar_term(date(2024, 2, 4)), Some("立春"));
+        assert_eq!(solar_term(date(2024, 2, 5)), None);
+    }
+}
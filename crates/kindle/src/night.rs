@@ -0,0 +1,30 @@
+use super::shared::*;
+use super::widgets::*;
+use anyhow::Result;
+use image::{GrayImage, Luma};
+
+/// an almost blank screen for the hours nobody looks at it
+pub fn generate(img: &mut GrayImage, context: &Context) -> Result<()> {
+    let frame = Frame::new(img, 600, 800);
+
+    compose(
+        img,
+        context,
+        &[
+            (frame.rect(0, 0, 600, 800), &Fill { color: Luma([255]) }),
+            (
+                frame.rect(0, 800 - 20 - 18, 600, 18),
+                &UpdateTimeWidget {
+                    style: TextStyle {
+                        font: "status",
+                        color: Luma([192]),
+                        align: (AlignHorizontal::Center, AlignVertical::Bottom),
+                    },
+                    text: |now, _| now.format("%Y-%m-%d %H:%M").to_string(),
+                },
+            ),
+        ],
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,178 @@
+// A photo frame: one picture per refresh from `kindle.photos.dir` under the
+// data path and, with `inbox = true`, the image files in the inbox, cropped
+// to the canvas and dithered, with the date and battery in a strip below.
+//
+// The pick is seeded by the device and the refresh period, so requests of
+// the same period get the same photo, and the `recent` photos a device has
+// shown are skipped while there are others.
+use super::device::DEFAULT_DEVICE;
+use super::dither::{quantize, Dither};
+use super::refresh;
+use super::shared::*;
+use super::widgets::*;
+use anyhow::{anyhow, Result};
+use image::{imageops::FilterType, io::Reader, GrayImage, Luma};
+use log::{info, warn};
+use once_cell::sync::OnceCell;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use utils::database::Db;
+
+const EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "bmp", "gif", "webp"];
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Photos {
+    /// relative to the data path
+    #[serde(default)]
+    pub dir: Option<String>,
+    #[serde(default)]
+    pub inbox: bool,
+    #[serde(default = "default_recent")]
+    pub recent: usize,
+}
+
+fn default_recent() -> usize {
+    10
+}
+
+static PHOTOS: OnceCell<Photos> = OnceCell::new();
+
+pub fn set_photos(photos: Photos) {
+    info!("kindle photos: {:?}", photos);
+    PHOTOS.get_or_init(|| photos);
+}
+
+/// photos a device has shown, newest first
+#[derive(Serialize, Deserialize, Default)]
+struct Shown {
+    period: i64,
+    recent: Vec<String>,
+}
+
+fn is_image(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|x| x.to_str())
+        .is_some_and(|x| EXTENSIONS.contains(&x.to_lowercase().as_str()))
+}
+
+fn candidates(photos: &Photos) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    if let Some(dir) = &photos.dir {
+        let dir = Path::new(utils::get_data_path()).join(dir);
+        match std::fs::read_dir(&dir) {
+            Ok(entries) => files.extend(
+                entries
+                    .flatten()
+                    .map(|x| x.path())
+                    .filter(|x| x.is_file() && is_image(&x.to_string_lossy())),
+            ),
+            Err(e) => warn!("failed to read {}: {}", dir.display(), e),
+        }
+    }
+    if photos.inbox {
+        files.extend(
+            inbox::files()
+                .into_iter()
+                .filter(|(name, _)| is_image(name))
+                .map(|(_, path)| path),
+        );
+    }
+    files.sort();
+    files
+}
+
+fn pick(photos: &Photos, context: &Context) -> Result<PathBuf> {
+    let candidates = candidates(photos);
+    if candidates.is_empty() {
+        return Err(anyhow!("no photos found"));
+    }
+    let device = context.device.as_deref().unwrap_or(DEFAULT_DEVICE);
+    let period = context.now.map_or(0, |x| x.timestamp()) / refresh::interval().max(1);
+    let key = format!("kindle/photo/{}", device);
+    let db = Db::new();
+    let mut shown: Shown = db.get(&key)?.unwrap_or_default();
+    if shown.period == period {
+        let last = shown.recent.first().map(PathBuf::from);
+        if let Some(last) = last.filter(|x| candidates.contains(x)) {
+            return Ok(last);
+        }
+    }
+    // leave at least one candidate to choose from
+    shown
+        .recent
+        .truncate(photos.recent.min(candidates.len() - 1));
+    let fresh: Vec<&PathBuf> = candidates
+        .iter()
+        .filter(|x| !shown.recent.iter().any(|y| Path::new(y) == x.as_path()))
+        .collect();
+    let mut hasher = DefaultHasher::new();
+    (device, period).hash(&mut hasher);
+    let photo = fresh
+        .choose(&mut StdRng::seed_from_u64(hasher.finish()))
+        .map(|x| x.to_path_buf())
+        .unwrap_or_else(|| candidates[0].clone());
+    shown.period = period;
+    shown.recent.insert(0, photo.to_string_lossy().to_string());
+    db.set(&key, &shown)?;
+    Ok(photo)
+}
+
+pub fn generate(img: &mut GrayImage, context: &Context) -> Result<()> {
+    let photos = PHOTOS
+        .get()
+        .ok_or(anyhow!("kindle.photos is not configured"))?;
+    let path = pick(photos, context)?;
+    info!("photo={}", path.display());
+    let (width, height) = img.dimensions();
+    let mut photo = Reader::open(&path)?
+        .with_guessed_format()?
+        .decode()?
+        .resize_to_fill(width, height, FilterType::Triangle)
+        .to_luma8();
+    let dither = match context.profile.dither {
+        None | Some(Dither::None) => Dither::FloydSteinberg,
+        Some(dither) => dither,
+    };
+    quantize(&mut photo, context.profile.levels.unwrap_or(16), dither);
+    *img = photo;
+
+    let frame = Frame::new(img, 600, 800);
+    let style = TextStyle {
+        font: "status",
+        color: Luma([0]),
+        align: (AlignHorizontal::Left, AlignVertical::Center),
+    };
+    compose(
+        img,
+        context,
+        &[
+            (
+                frame.rect(0, 800 - 32, 600, 32),
+                &Fill { color: Luma([255]) },
+            ),
+            (
+                frame.rect(12, 800 - 32 + 7, 400, 18),
+                &UpdateTimeWidget {
+                    style,
+                    text: |now, _| now.format("%Y-%m-%d %H:%M").to_string(),
+                },
+            ),
+            (
+                frame.rect(600 - 12 - 150, 800 - 32 + 7, 150, 18),
+                &BatteryWidget {
+                    style: TextStyle {
+                        align: (AlignHorizontal::Right, AlignVertical::Center),
+                        ..style
+                    },
+                    text: |battery, _| format!("{}%", battery),
+                },
+            ),
+        ],
+    )?;
+
+    Ok(())
+}
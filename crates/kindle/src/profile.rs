@@ -0,0 +1,121 @@
+use super::dither::Dither;
+use super::encode::Format;
+use anyhow::{anyhow, Result};
+use image::{imageops, GrayImage, Luma};
+use log::info;
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(try_from = "u32")]
+pub enum Rotation {
+    None,
+    Clockwise90,
+    Clockwise180,
+    Clockwise270,
+}
+
+impl TryFrom<u32> for Rotation {
+    type Error = String;
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Rotation::None),
+            90 => Ok(Rotation::Clockwise90),
+            180 => Ok(Rotation::Clockwise180),
+            270 => Ok(Rotation::Clockwise270),
+            _ => Err(format!("invalid rotation {}", value)),
+        }
+    }
+}
+
+/// `width` and `height` are the pixels of the panel itself, the screen is
+/// drawn on a canvas turned by `rotation` and rotated back before encoding.
+/// there is no dpi: designs are scaled by pixels to fill the canvas, so an
+/// old `dpi` key is refused rather than silently ignored
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub width: u32,
+    pub height: u32,
+    #[serde(default = "default_rotation")]
+    pub rotation: Rotation,
+    /// gray levels of the panel, the output is not quantized if absent
+    #[serde(default)]
+    pub levels: Option<u32>,
+    #[serde(default)]
+    pub dither: Option<Dither>,
+    #[serde(default)]
+    pub format: Option<Format>,
+}
+
+fn default_rotation() -> Rotation {
+    Rotation::None
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Profile {
+            width: 600,
+            height: 800,
+            rotation: default_rotation(),
+            levels: None,
+            dither: None,
+            format: None,
+        }
+    }
+}
+
+impl Profile {
+    pub fn canvas_size(&self) -> (u32, u32) {
+        match self.rotation {
+            Rotation::None | Rotation::Clockwise180 => (self.width, self.height),
+            Rotation::Clockwise90 | Rotation::Clockwise270 => (self.height, self.width),
+        }
+    }
+
+    /// white, the margins left by designs of another aspect ratio stay so
+    pub fn canvas(&self) -> GrayImage {
+        let (width, height) = self.canvas_size();
+        GrayImage::from_pixel(width, height, Luma([255]))
+    }
+
+    pub fn rotate(&self, img: GrayImage) -> GrayImage {
+        match self.rotation {
+            Rotation::None => img,
+            Rotation::Clockwise90 => imageops::rotate90(&img),
+            Rotation::Clockwise180 => imageops::rotate180(&img),
+            Rotation::Clockwise270 => imageops::rotate270(&img),
+        }
+    }
+}
+
+struct Profiles {
+    default: Option<String>,
+    profiles: HashMap<String, Profile>,
+}
+
+static PROFILES: OnceCell<Profiles> = OnceCell::new();
+
+pub fn load_profiles(profiles: HashMap<String, Profile>, default: Option<String>) {
+    PROFILES.get_or_init(|| {
+        for (name, profile) in profiles.iter() {
+            info!("device profile {}: {:?}", name, profile);
+        }
+        Profiles { default, profiles }
+    });
+}
+
+pub fn get_profile(name: Option<&str>) -> Result<Profile> {
+    let Some(profiles) = PROFILES.get() else {
+        return Ok(Profile::default());
+    };
+    match name.or(profiles.default.as_deref()) {
+        Some(name) => profiles
+            .profiles
+            .get(name)
+            .cloned()
+            .ok_or(anyhow!("unknown profile = {}", name)),
+        None => Ok(Profile::default()),
+    }
+}
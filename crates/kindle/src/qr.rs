@@ -0,0 +1,92 @@
+// QR codes drawn with a whole number of pixels per module, so the edges
+// stay sharp on the panel and survive dithering. The payload of `QrWidget`
+// and layout `qr` elements comes from `kindle.qr`: a fixed `text` such as a
+// Wi-Fi join string, or a link to the newest public inbox item.
+use anyhow::{anyhow, Result};
+use image::{GrayImage, Luma};
+use imageproc::{drawing, rect::Rect};
+use log::info;
+use once_cell::sync::OnceCell;
+use qrcode::{Color, EcLevel, QrCode};
+use serde::Deserialize;
+
+/// modules of white margin around the code
+const QUIET_ZONE: u32 = 4;
+
+#[derive(Deserialize, Clone, Default)]
+pub struct Qr {
+    /// such as WIFI:T:WPA;S:<ssid>;P:<password>;;
+    #[serde(default)]
+    pub text: Option<String>,
+    /// public url of the inbox get route like http://192.168.1.2:8000/inbox/api/get/,
+    /// the newest public item is linked instead of `text` when there is one
+    #[serde(default)]
+    pub inbox: Option<String>,
+}
+
+static QR: OnceCell<Qr> = OnceCell::new();
+
+pub fn set_qr(qr: Qr) {
+    // the text may hold a password, keep it out of the log
+    info!(
+        "kindle qr: text={}, inbox={:?}",
+        qr.text.is_some(),
+        qr.inbox
+    );
+    QR.get_or_init(|| qr);
+}
+
+/// the configured payload
+pub fn qr_payload() -> Option<String> {
+    let qr = QR.get()?;
+    qr.inbox
+        .as_ref()
+        .and_then(|url| inbox::newest().map(|id| format!("{}{}", url, id)))
+        .or_else(|| qr.text.clone())
+}
+
+fn encode(payload: &str) -> Result<QrCode> {
+    Ok(QrCode::with_error_correction_level(payload, EcLevel::M)?)
+}
+
+fn draw_code(canvas: &mut GrayImage, rect: Rect, code: &QrCode) -> Result<Rect> {
+    let width = code.width() as u32;
+    let total = width + 2 * QUIET_ZONE;
+    let module = rect.width().min(rect.height()) / total;
+    if module == 0 {
+        return Err(anyhow!(
+            "{}x{} is too small for a qr code of {} modules",
+            rect.width(),
+            rect.height(),
+            total
+        ));
+    }
+    let size = total * module;
+    let left = rect.left() + (rect.width() - size) as i32 / 2;
+    let top = rect.top() + (rect.height() - size) as i32 / 2;
+    let area = Rect::at(left, top).of_size(size, size);
+    drawing::draw_filled_rect_mut(canvas, area, Luma([255]));
+    for (i, color) in code.to_colors().into_iter().enumerate() {
+        if color == Color::Dark {
+            let x = (i as u32 % width + QUIET_ZONE) * module;
+            let y = (i as u32 / width + QUIET_ZONE) * module;
+            let rect = Rect::at(left + x as i32, top + y as i32).of_size(module, module);
+            drawing::draw_filled_rect_mut(canvas, rect, Luma([0]));
+        }
+    }
+    Ok(area)
+}
+
+/// draw `payload` centered in `rect` with the largest module that fits
+pub fn draw_qr(canvas: &mut GrayImage, rect: Rect, payload: &str) -> Result<Rect> {
+    draw_code(canvas, rect, &encode(payload)?)
+}
+
+/// the code alone, `module` pixels per module
+pub fn qr_image(payload: &str, module: u32) -> Result<GrayImage> {
+    let code = encode(payload)?;
+    let size = (code.width() as u32 + 2 * QUIET_ZONE) * module.max(1);
+    let mut img = GrayImage::new(size, size);
+    draw_code(&mut img, Rect::at(0, 0).of_size(size, size), &code)?;
+    Ok(img)
+}
@@ -0,0 +1,128 @@
+// The quote of the day from a corpus in the data path, `kindle.quotes`: a
+// JSON array of `{"text": ..., "author": ...}`, or with a .tsv extension one
+// quote per line followed by a tab and its author. The file is read on every
+// render, so additions show up without a restart, and every device shows
+// the same quote on the same date. Quotes appended by `add_quote` carry the
+// date they were added and join the pick the day after, so today's quote
+// stays put.
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Local, NaiveDate};
+use log::{info, warn};
+use once_cell::sync::{Lazy, OnceCell};
+use serde::{Deserialize, Serialize};
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Hash)]
+pub struct Quote {
+    pub text: String,
+    #[serde(default)]
+    pub author: Option<String>,
+    /// set by `add_quote`, a third column in .tsv files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added: Option<NaiveDate>,
+}
+
+static QUOTES: OnceCell<Option<String>> = OnceCell::new();
+/// held while the corpus is rewritten
+static WRITING: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+pub fn set_quotes(path: Option<String>) {
+    if let Some(path) = QUOTES.get_or_init(|| path) {
+        info!("kindle quotes from {}", path);
+    }
+}
+
+fn path() -> Result<&'static str> {
+    QUOTES
+        .get()
+        .and_then(|x| x.as_deref())
+        .ok_or(anyhow!("kindle.quotes is not configured"))
+}
+
+fn is_tsv(path: &str) -> bool {
+    Path::new(path).extension().is_some_and(|x| x == "tsv")
+}
+
+fn parse_tsv(raw: &str) -> Vec<Quote> {
+    raw.lines()
+        .filter(|x| !x.trim().is_empty())
+        .map(|line| {
+            let mut columns = line.split('\t').map(|x| x.trim());
+            let text = columns.next().unwrap_or_default();
+            let author = columns.next().unwrap_or_default();
+            Quote {
+                text: text.to_string(),
+                author: Some(author.to_string()).filter(|x| !x.is_empty()),
+                added: columns.next().and_then(|x| x.parse().ok()),
+            }
+        })
+        .collect()
+}
+
+pub fn load_quotes() -> Result<Vec<Quote>> {
+    let path = path()?;
+    let raw = match std::fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    match is_tsv(path) {
+        true => Ok(parse_tsv(&raw)),
+        false if raw.trim().is_empty() => Ok(Vec::new()),
+        false => Ok(serde_json::from_str(&raw)?),
+    }
+}
+
+/// the quote shown on `date`, `None` without a corpus
+pub fn quote_of(date: NaiveDate) -> Option<Quote> {
+    QUOTES.get()?.as_ref()?;
+    let quotes = load_quotes()
+        .map_err(|e| warn!("failed to load quotes: {:?}", e))
+        .ok()?;
+    let quotes: Vec<Quote> = quotes
+        .into_iter()
+        .filter(|x| x.added.is_none_or(|added| added < date))
+        .collect();
+    if quotes.is_empty() {
+        return None;
+    }
+    let index = date.num_days_from_ce().rem_euclid(quotes.len() as i32) as usize;
+    quotes.into_iter().nth(index)
+}
+
+/// append `quote` to the corpus, returns how many it holds
+pub fn add_quote(quote: Quote) -> Result<usize> {
+    let path = path()?;
+    let quote = Quote {
+        added: Some(Local::now().date_naive()),
+        ..quote
+    };
+    let _writing = WRITING.lock().unwrap();
+    if is_tsv(path) {
+        // tabs and newlines would split the entry
+        let clean = |x: &str| x.split_whitespace().collect::<Vec<_>>().join(" ");
+        let raw = std::fs::read_to_string(path).unwrap_or_default();
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        if !raw.is_empty() && !raw.ends_with('\n') {
+            writeln!(file)?;
+        }
+        writeln!(
+            file,
+            "{}\t{}\t{}",
+            clean(&quote.text),
+            clean(quote.author.as_deref().unwrap_or_default()),
+            quote.added.map(|x| x.to_string()).unwrap_or_default()
+        )?;
+        Ok(parse_tsv(&raw).len() + 1)
+    } else {
+        let mut quotes = load_quotes()?;
+        quotes.push(quote);
+        std::fs::write(path, serde_json::to_string_pretty(&quotes)?)?;
+        Ok(quotes.len())
+    }
+}
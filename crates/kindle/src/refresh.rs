@@ -0,0 +1,107 @@
+// When a client should wake up next: after `interval`, or right after the
+// date or the scheduled style changes, whichever comes first. Inside the
+// quiet hours it sleeps until they end, and a low battery stretches the
+// interval.
+use super::schedule::{boundaries, Clock};
+use chrono::{DateTime, Duration, FixedOffset, NaiveTime};
+use log::info;
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+
+/// wake up a little after a change so a drifting clock does not miss it
+const MARGIN: i64 = 60;
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct Quiet {
+    pub from: Clock,
+    pub to: Clock,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Refresh {
+    /// seconds
+    #[serde(default = "default_interval")]
+    pub interval: i64,
+    #[serde(default)]
+    pub quiet: Option<Quiet>,
+    /// percent
+    #[serde(default = "default_low_battery")]
+    pub low_battery: usize,
+    /// seconds, used below `low_battery`
+    #[serde(default = "default_low_battery_interval")]
+    pub low_battery_interval: i64,
+}
+
+fn default_interval() -> i64 {
+    30 * 60
+}
+
+fn default_low_battery() -> usize {
+    20
+}
+
+fn default_low_battery_interval() -> i64 {
+    2 * 3600
+}
+
+impl Default for Refresh {
+    fn default() -> Self {
+        Refresh {
+            interval: default_interval(),
+            quiet: None,
+            low_battery: default_low_battery(),
+            low_battery_interval: default_low_battery_interval(),
+        }
+    }
+}
+
+static REFRESH: OnceCell<Refresh> = OnceCell::new();
+
+pub fn set_refresh(refresh: Refresh) {
+    info!("kindle refresh: {:?}", refresh);
+    REFRESH.get_or_init(|| refresh);
+}
+
+/// seconds between refreshes in normal hours
+pub fn interval() -> i64 {
+    REFRESH.get().map_or(default_interval(), |x| x.interval)
+}
+
+/// the first `time` of day strictly after `now`
+fn next_time(now: DateTime<FixedOffset>, time: NaiveTime) -> DateTime<FixedOffset> {
+    let today = now.date_naive().and_time(time);
+    let naive = match today > now.naive_local() {
+        true => today,
+        false => today + Duration::days(1),
+    };
+    naive.and_local_timezone(*now.offset()).unwrap()
+}
+
+fn in_quiet(quiet: &Quiet, time: NaiveTime) -> bool {
+    match quiet.from.0 <= quiet.to.0 {
+        true => quiet.from.0 <= time && time < quiet.to.0,
+        false => time >= quiet.from.0 || time < quiet.to.0,
+    }
+}
+
+pub fn next_refresh(now: DateTime<FixedOffset>, battery: Option<usize>) -> DateTime<FixedOffset> {
+    let default = Refresh::default();
+    let refresh = REFRESH.get().unwrap_or(&default);
+    let margin = Duration::seconds(MARGIN);
+    if let Some(quiet) = refresh.quiet.filter(|x| in_quiet(x, now.time())) {
+        return next_time(now, quiet.to.0) + margin;
+    }
+    let interval = match battery {
+        Some(battery) if battery < refresh.low_battery => refresh.low_battery_interval,
+        _ => refresh.interval,
+    };
+    let mut changes = boundaries();
+    changes.push(NaiveTime::MIN);
+    changes.extend(refresh.quiet.map(|x| x.from.0));
+    changes
+        .into_iter()
+        .map(|x| next_time(now, x) + margin)
+        .chain([now + Duration::seconds(interval.max(MARGIN))])
+        .min()
+        .unwrap()
+}
@@ -0,0 +1,145 @@
+// Styles by the time of day, checked in order against `Context.now`:
+//
+//     schedule = [
+//         { from = "06:30", to = "09:00", days = "workdays", style = "alpha" },
+//         { from = "23:00", to = "06:30", style = "night" },
+//         { style = "delta" },
+//     ]
+//
+// `from` is inclusive and `to` exclusive, a range with `to` before `from`
+// runs past midnight. Missing bounds mean the start or end of the day.
+use super::shared::Context;
+use chrono::{Datelike, NaiveDate, NaiveTime, Weekday};
+use log::info;
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(try_from = "String")]
+pub struct Clock(pub NaiveTime);
+
+impl TryFrom<String> for Clock {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "24:00" => Ok(Clock(NaiveTime::MIN)),
+            _ => NaiveTime::parse_from_str(&value, "%H:%M")
+                .map(Clock)
+                .map_err(|e| format!("invalid time {}: {}", value, e)),
+        }
+    }
+}
+
+/// which days a slot applies to
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(try_from = "String")]
+pub enum Days {
+    #[default]
+    All,
+    /// monday to friday
+    Weekdays,
+    Weekends,
+    /// working days by the holiday calendar, adjusted weekends included
+    Workdays,
+    Offdays,
+    List(Vec<Weekday>),
+}
+
+impl TryFrom<String> for Days {
+    type Error = String;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Ok(match value.as_str() {
+            "" | "all" => Days::All,
+            "weekdays" => Days::Weekdays,
+            "weekends" => Days::Weekends,
+            "workdays" => Days::Workdays,
+            "offdays" => Days::Offdays,
+            list => Days::List(
+                list.split(',')
+                    .map(|x| x.trim().parse::<Weekday>())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| format!("invalid days {}", value))?,
+            ),
+        })
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Slot {
+    #[serde(default)]
+    pub from: Option<Clock>,
+    #[serde(default)]
+    pub to: Option<Clock>,
+    #[serde(default)]
+    pub days: Days,
+    pub style: String,
+}
+
+impl Slot {
+    fn covers(&self, time: NaiveTime) -> bool {
+        let from = self.from.map_or(NaiveTime::MIN, |x| x.0);
+        match self.to.map(|x| x.0).filter(|x| *x != NaiveTime::MIN) {
+            None => time >= from,
+            Some(to) if from < to => from <= time && time < to,
+            Some(to) => time >= from || time < to,
+        }
+    }
+
+    fn applies(&self, date: NaiveDate) -> bool {
+        let weekend = matches!(date.weekday(), Weekday::Sat | Weekday::Sun);
+        match &self.days {
+            Days::All => true,
+            Days::Weekdays => !weekend,
+            Days::Weekends => weekend,
+            Days::Workdays => holiday::is_workday(date),
+            Days::Offdays => !holiday::is_workday(date),
+            Days::List(list) => list.contains(&date.weekday()),
+        }
+    }
+
+    fn matches(&self, context: &Context) -> bool {
+        let Some(now) = context.now else {
+            return self.from.is_none() && self.to.is_none();
+        };
+        let time = now.time();
+        if !self.covers(time) {
+            return false;
+        }
+        // a slot running past midnight belongs to the day it starts on
+        let date = now.date_naive();
+        match self.from.is_some_and(|x| time < x.0) {
+            true => date.pred_opt().is_some_and(|x| self.applies(x)),
+            false => self.applies(date),
+        }
+    }
+}
+
+static SCHEDULE: OnceCell<Vec<Slot>> = OnceCell::new();
+
+pub fn set_schedule(schedule: Vec<Slot>) {
+    for slot in schedule.iter() {
+        info!("kindle schedule: {:?}", slot);
+    }
+    SCHEDULE.get_or_init(|| schedule);
+}
+
+/// style of the first matching slot
+pub fn scheduled_style(context: &Context) -> Option<&'static str> {
+    SCHEDULE
+        .get()?
+        .iter()
+        .find(|x| x.matches(context))
+        .map(|x| x.style.as_str())
+}
+
+/// times of day the scheduled style may change
+pub fn boundaries() -> Vec<NaiveTime> {
+    SCHEDULE
+        .get()
+        .into_iter()
+        .flatten()
+        .flat_map(|x| [x.from, x.to])
+        .flatten()
+        .map(|x| x.0)
+        .collect()
+}
@@ -1,12 +1,21 @@
 use std::collections::HashMap;
 
-use image::{GenericImageView, GrayAlphaImage, GrayImage, Luma, LumaA};
-use imageproc::{drawing, rect::Rect};
+use anyhow::{anyhow, Result};
+use image::{GrayImage, Luma};
+use imageproc::rect::Rect;
 use log::info;
 use once_cell::sync::OnceCell;
 use rusttype::{Font, Scale};
+use serde::Deserialize;
+
+use super::font::FontStack;
+use super::history::BatteryHistory;
+use super::locale::Locale;
+use super::profile::Profile;
 
 #[allow(dead_code)]
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
 pub enum AlignHorizontal {
     Left,
     Center,
@@ -14,76 +23,37 @@ pub enum AlignHorizontal {
 }
 
 #[allow(dead_code)]
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
 pub enum AlignVertical {
     Top,
     Center,
     Bottom,
 }
 
+/// draw text with its inked pixels centered on `base`, blank text draws nothing
 pub fn draw_centered_text<'a>(
     canvas: &'a mut GrayImage,
     color: Luma<u8>,
     base: (i32, i32),
     scale: Scale,
-    font: &'a Font<'a>,
+    font: &'a FontStack,
     text: &'a str,
 ) -> Rect {
-    let size = drawing::text_size(scale, font, &text);
-    let temp = drawing::draw_text(
-        &GrayAlphaImage::new(size.0 as u32, size.1 as u32),
-        LumaA([color.0[0], 255]),
-        0,
-        0,
+    let Some(bounds) = font.bounds(scale, text) else {
+        return Rect::at(base.0, base.1).of_size(1, 1);
+    };
+    let x = base.0 - bounds.width() as i32 / 2;
+    let y = base.1 - bounds.height() as i32 / 2;
+    font.draw_text_mut(
+        canvas,
+        color,
+        x - bounds.left(),
+        y - bounds.top(),
         scale,
-        font,
-        &text,
+        text,
     );
-    let temp = {
-        let mut min_x = size.0 as u32;
-        let mut max_x = 0 as u32;
-        let mut min_y = size.1 as u32;
-        let mut max_y = 0 as u32;
-        for x in 0..(size.0 as u32) {
-            for y in 0..(size.1 as u32) {
-                if temp.get_pixel(x, y).0[1] as u32 > 0 {
-                    if x < min_x {
-                        min_x = x
-                    };
-                    if x > max_x {
-                        max_x = x
-                    };
-                    if y < min_y {
-                        min_y = y
-                    };
-                    if y > max_y {
-                        max_y = y
-                    };
-                }
-            }
-        }
-        image::imageops::crop_imm(&temp, min_x, min_y, max_x - min_x, max_y - min_y)
-    };
-
-    let size = (temp.width() as i32, temp.height() as i32);
-    let x = base.0 - size.0 / 2;
-    let y = base.1 - size.1 / 2;
-    for w in 0..size.0 {
-        for h in 0..size.1 {
-            if (w + x < 0)
-                || (h + y < 0)
-                || (w + x >= canvas.width() as i32)
-                || (h + y >= canvas.height() as i32)
-            {
-                continue;
-            }
-            let a = canvas.get_pixel((w + x) as u32, (h + y) as u32);
-            let b = temp.get_pixel(w as u32, h as u32);
-            let r = b.0[1] as f32 / 255.0;
-            let p = Luma([((a.0[0] as f32) * (1.0 - r) + (b.0[0] as f32) * r) as u8]);
-            canvas.put_pixel((w + x) as u32, (h + y) as u32, p);
-        }
-    }
-    Rect::at(x, y).of_size(size.0.try_into().unwrap(), size.1.try_into().unwrap())
+    Rect::at(x, y).of_size(bounds.width(), bounds.height())
 }
 
 pub fn draw_aligned_text<'a>(
@@ -91,11 +61,11 @@ pub fn draw_aligned_text<'a>(
     color: Luma<u8>,
     base: (i32, i32),
     scale: Scale,
-    font: &'a Font<'a>,
+    font: &'a FontStack,
     text: &'a str,
     align: (AlignHorizontal, AlignVertical),
 ) -> Rect {
-    let size = drawing::text_size(scale, font, &text);
+    let size = font.text_size(scale, text);
     let x = match align.0 {
         AlignHorizontal::Left => base.0,
         AlignHorizontal::Center => base.0 - size.0 / 2,
@@ -106,33 +76,466 @@ pub fn draw_aligned_text<'a>(
         AlignVertical::Center => base.1 - size.1 / 2,
         AlignVertical::Bottom => base.1 - size.1,
     };
-    drawing::draw_text_mut(canvas, color, x, y, scale, font, &text);
-    return Rect::at(x, y).of_size(size.0.try_into().unwrap(), size.1.try_into().unwrap());
+    font.draw_text_mut(canvas, color, x, y, scale, text);
+    Rect::at(x, y).of_size(size.0.max(1) as u32, size.1.max(1) as u32)
 }
 
+#[derive(Default)]
 pub struct Context {
+    /// id of the requesting device
+    pub device: Option<String>,
     pub battery: Option<usize>,
-    pub now: Option<chrono::DateTime<chrono::Local>>,
+    pub battery_history: BatteryHistory,
+    /// wall clock time of the device
+    pub now: Option<chrono::DateTime<chrono::FixedOffset>>,
+    pub forecast_24h: Option<qweather::Forcast24H>,
+    pub forecast_3d: Option<Vec<qweather::DailyForecast>>,
+    pub holiday: Option<holiday::Day>,
+    /// events of the day
+    pub agenda: Vec<agenda::Event>,
+    pub profile: Profile,
+    pub locale: Locale,
+}
+
+impl Context {
+    pub async fn fetch(&mut self) {
+        self.forecast_24h = qweather::get_24h_forcast().await.ok();
+        self.forecast_3d = qweather::get_3d_forecast().await.ok();
+        self.holiday = self.now.map(|x| holiday::query(x.date_naive()));
+        if let Some(now) = self.now {
+            // the day of the device, which may be elsewhere than the server
+            let midnight = |date: chrono::NaiveDate| {
+                date.and_hms_opt(0, 0, 0)
+                    .and_then(|x| x.and_local_timezone(*now.offset()).single())
+                    .map(|x| x.with_timezone(&chrono::Local))
+            };
+            let date = now.date_naive();
+            if let (Some(from), Some(to)) =
+                (midnight(date), midnight(date + chrono::Duration::days(1)))
+            {
+                self.agenda = agenda::events(from, to);
+            }
+        }
+    }
+
+    /// `time` on the clock of the device
+    pub fn local(
+        &self,
+        time: chrono::DateTime<chrono::Local>,
+    ) -> chrono::DateTime<chrono::FixedOffset> {
+        match self.now {
+            Some(now) => time.with_timezone(now.offset()),
+            None => time.fixed_offset(),
+        }
+    }
 }
 
-static FONTS: OnceCell<HashMap<String, Font>> = OnceCell::new();
-pub fn load_fonts(fonts: HashMap<String, String>) {
+static FONTS: OnceCell<HashMap<String, FontStack>> = OnceCell::new();
+static FONT_FILES: OnceCell<HashMap<String, Vec<String>>> = OnceCell::new();
+/// font stacks by name, each a list of font files tried in order
+pub fn load_fonts(fonts: HashMap<String, Vec<String>>) {
+    FONT_FILES.get_or_init(|| fonts.clone());
     FONTS.get_or_init(|| {
+        let mut files: HashMap<String, Font<'static>> = HashMap::new();
         let mut map = HashMap::new();
-        for x in fonts.iter() {
-            let name = x.0;
-            let path = x.1;
-            info!("loading {}: {}", name, path);
-            let data = std::fs::read(&path).expect(&format!("failed to load {}", path));
-            let font = Font::try_from_vec(data).unwrap_or_else(|| {
-                panic!("cannot load {}", path);
-            });
-            map.insert(name.to_string(), font);
+        for (name, paths) in fonts.iter() {
+            let mut stack = Vec::new();
+            for path in paths {
+                info!("loading {}: {}", name, path);
+                let font = files.entry(path.clone()).or_insert_with(|| {
+                    let data = std::fs::read(path)
+                        .unwrap_or_else(|e| panic!("failed to load {}: {}", path, e));
+                    Font::try_from_vec(data).unwrap_or_else(|| {
+                        panic!("cannot load {}", path);
+                    })
+                });
+                stack.push(font.clone());
+            }
+            if !stack.is_empty() {
+                map.insert(name.to_string(), FontStack::new(name, stack));
+            }
         }
         return map;
     });
 }
 
-pub fn get_font(name: &str) -> Option<&Font> {
+pub fn get_font(name: &str) -> Option<&'static FontStack> {
     return FONTS.get().unwrap().get(name);
 }
+
+/// files of the font stacks by name, for renderers other than rusttype
+pub fn font_files() -> Option<&'static HashMap<String, Vec<String>>> {
+    FONT_FILES.get()
+}
+
+pub trait Widget {
+    fn render(&self, canvas: &mut GrayImage, rect: Rect, context: &Context) -> Result<()>;
+}
+
+pub fn compose(
+    canvas: &mut GrayImage,
+    context: &Context,
+    widgets: &[(Rect, &dyn Widget)],
+) -> Result<()> {
+    for (rect, widget) in widgets {
+        widget.render(canvas, *rect, context)?;
+    }
+    Ok(())
+}
+
+/// maps coordinates of a fixed size design onto the canvas, scaled evenly
+/// and centered so a canvas of another aspect ratio gets margins instead of
+/// stretching the design
+#[derive(Clone, Copy)]
+pub struct Frame {
+    scale: f32,
+    dx: f32,
+    dy: f32,
+}
+
+impl Frame {
+    pub fn new(canvas: &GrayImage, width: u32, height: u32) -> Frame {
+        let scale =
+            (canvas.width() as f32 / width as f32).min(canvas.height() as f32 / height as f32);
+        Frame {
+            scale,
+            dx: (canvas.width() as f32 - width as f32 * scale) / 2.0,
+            dy: (canvas.height() as f32 - height as f32 * scale) / 2.0,
+        }
+    }
+
+    pub fn x(&self, x: i32) -> i32 {
+        (self.dx + x as f32 * self.scale).round() as i32
+    }
+
+    pub fn y(&self, y: i32) -> i32 {
+        (self.dy + y as f32 * self.scale).round() as i32
+    }
+
+    pub fn scale(&self, scale: f32) -> f32 {
+        scale * self.scale
+    }
+
+    pub fn rect(&self, x: i32, y: i32, width: u32, height: u32) -> Rect {
+        let width = (width as f32 * self.scale).round().max(1.0) as u32;
+        let height = (height as f32 * self.scale).round().max(1.0) as u32;
+        Rect::at(self.x(x), self.y(y)).of_size(width, height)
+    }
+}
+
+/// the point of `rect` which the text is aligned to
+pub fn anchor(rect: Rect, align: (AlignHorizontal, AlignVertical)) -> (i32, i32) {
+    let x = match align.0 {
+        AlignHorizontal::Left => rect.left(),
+        AlignHorizontal::Center => rect.left() + rect.width() as i32 / 2,
+        AlignHorizontal::Right => rect.left() + rect.width() as i32,
+    };
+    let y = match align.1 {
+        AlignVertical::Top => rect.top(),
+        AlignVertical::Center => rect.top() + rect.height() as i32 / 2,
+        AlignVertical::Bottom => rect.top() + rect.height() as i32,
+    };
+    (x, y)
+}
+
+#[derive(Clone, Copy)]
+pub struct TextStyle {
+    pub font: &'static str,
+    pub color: Luma<u8>,
+    pub align: (AlignHorizontal, AlignVertical),
+}
+
+impl TextStyle {
+    /// draw a single line of text inside `rect`, scaled to its height and
+    /// shrunk if it is wider than the rect
+    pub fn draw(&self, canvas: &mut GrayImage, rect: Rect, text: &str) -> Result<Rect> {
+        let font = get_font(self.font).ok_or(anyhow!("{} font not found", self.font))?;
+        let mut scale = rect.height() as f32;
+        let (width, _) = font.text_size(Scale::uniform(scale), text);
+        if width > rect.width() as i32 {
+            scale *= rect.width() as f32 / width as f32;
+        }
+        Ok(draw_aligned_text(
+            canvas,
+            self.color,
+            anchor(rect, self.align),
+            Scale::uniform(scale),
+            font,
+            text,
+            self.align,
+        ))
+    }
+}
+
+/// may not begin a line
+const NO_START: &str = "，。、！？：；）」』】》〉”’…·,.!?:;)]}%";
+/// may not end a line
+const NO_END: &str = "（「『【《〈“‘([{";
+
+/// ideographs, kana, hangul and fullwidth forms, which break anywhere
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x2E80..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF | 0xFF00..=0xFFEF | 0x20000..=0x2FFFF)
+}
+
+/// pieces a line may break between: words, single CJK characters with their
+/// punctuation attached, and spaces
+fn break_units(text: &str) -> Vec<String> {
+    let mut units: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for c in text.chars() {
+        let last = current.chars().last();
+        if c.is_whitespace() {
+            if !current.is_empty() {
+                units.push(std::mem::take(&mut current));
+            }
+            if units.last().is_some_and(|x| x != " ") {
+                units.push(" ".to_string());
+            }
+        } else if NO_START.contains(c) {
+            match (current.is_empty(), units.last_mut()) {
+                (true, Some(unit)) if unit != " " => unit.push(c),
+                _ => current.push(c),
+            }
+        } else if last.is_some_and(|x| !NO_END.contains(x) && (is_cjk(c) || is_cjk(x))) {
+            units.push(std::mem::replace(&mut current, c.to_string()));
+        } else {
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        units.push(current);
+    }
+    units
+}
+
+fn text_width(font: &FontStack, scale: Scale, text: &str) -> u32 {
+    font.text_size(scale, text).0.max(0) as u32
+}
+
+/// greedy line breaking to `width`, words wider than a line are split
+/// between characters
+pub fn wrap_text(font: &FontStack, scale: Scale, text: &str, width: u32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for unit in break_units(paragraph) {
+            let candidate = format!("{}{}", line, unit);
+            if text_width(font, scale, candidate.trim_end()) <= width {
+                line = candidate;
+                continue;
+            }
+            if !line.trim().is_empty() {
+                lines.push(line.trim_end().to_string());
+            }
+            line = String::new();
+            if unit == " " {
+                continue;
+            }
+            for c in unit.chars() {
+                let candidate = format!("{}{}", line, c);
+                if !line.is_empty() && text_width(font, scale, &candidate) > width {
+                    lines.push(std::mem::replace(&mut line, c.to_string()));
+                } else {
+                    line = candidate;
+                }
+            }
+        }
+        lines.push(line.trim_end().to_string());
+    }
+    lines
+}
+
+/// cut `line` so that it fits `width` with a trailing ellipsis
+pub fn ellipsize(font: &FontStack, scale: Scale, line: &str, width: u32) -> String {
+    let mut line = line.trim_end().to_string();
+    loop {
+        let candidate = format!("{}…", line);
+        if line.is_empty() || text_width(font, scale, &candidate) <= width {
+            return candidate;
+        }
+        line.pop();
+        line = line.trim_end().to_string();
+    }
+}
+
+/// text wrapped into a rect, at the largest scale between `min_scale` and
+/// `max_scale` which fits, cut with an ellipsis if even the smallest does not
+#[derive(Clone, Copy)]
+pub struct ParagraphStyle {
+    pub font: &'static str,
+    pub color: Luma<u8>,
+    pub align: (AlignHorizontal, AlignVertical),
+    /// the height of the rect if absent
+    pub max_scale: Option<f32>,
+    pub min_scale: f32,
+    /// 0 for as many as fit
+    pub max_lines: usize,
+    /// distance between baselines relative to the scale
+    pub line_spacing: f32,
+}
+
+impl ParagraphStyle {
+    fn capacity(&self, scale: f32, rect: Rect) -> usize {
+        let height = rect.height() as f32;
+        let lines = match scale > height {
+            true => 0,
+            false => ((height - scale) / (scale * self.line_spacing)) as usize + 1,
+        };
+        match self.max_lines {
+            0 => lines,
+            max => lines.min(max),
+        }
+    }
+
+    /// lines and scale the text is drawn with
+    pub fn fit(&self, font: &FontStack, rect: Rect, text: &str) -> (Vec<String>, f32) {
+        let fits = |scale: f32| {
+            let lines = wrap_text(font, Scale::uniform(scale), text, rect.width());
+            let fit = lines.len() <= self.capacity(scale, rect)
+                && lines
+                    .iter()
+                    .all(|x| text_width(font, Scale::uniform(scale), x) <= rect.width());
+            (fit, lines)
+        };
+        let max = self.max_scale.unwrap_or(rect.height() as f32);
+        let min = self.min_scale.min(max).max(1.0);
+        if let (true, lines) = fits(max) {
+            return (lines, max);
+        }
+        let (mut low, mut high) = (min, max);
+        let mut best = match fits(min) {
+            (true, lines) => Some((lines, min)),
+            _ => None,
+        };
+        while best.is_some() && high - low > 0.5 {
+            let middle = (low + high) / 2.0;
+            match fits(middle) {
+                (true, lines) => {
+                    best = Some((lines, middle));
+                    low = middle;
+                }
+                _ => high = middle,
+            }
+        }
+        if let Some(best) = best {
+            return best;
+        }
+        let (_, mut lines) = fits(min);
+        let capacity = self.capacity(min, rect).max(1);
+        if lines.len() > capacity {
+            lines.truncate(capacity);
+            let last = lines.pop().unwrap_or_default();
+            lines.push(ellipsize(font, Scale::uniform(min), &last, rect.width()));
+        }
+        (lines, min)
+    }
+
+    /// returns the rect the lines occupy
+    pub fn draw(&self, canvas: &mut GrayImage, rect: Rect, text: &str) -> Result<Rect> {
+        let font = get_font(self.font).ok_or(anyhow!("{} font not found", self.font))?;
+        let (lines, scale) = self.fit(font, rect, text);
+        let step = scale * self.line_spacing;
+        let height = scale + step * lines.len().saturating_sub(1) as f32;
+        let top = rect.top() as f32
+            + match self.align.1 {
+                AlignVertical::Top => 0.0,
+                AlignVertical::Center => (rect.height() as f32 - height) / 2.0,
+                AlignVertical::Bottom => rect.height() as f32 - height,
+            };
+        let mut occupied: Option<Rect> = None;
+        for (i, line) in lines.iter().enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+            let y = (top + step * i as f32).round() as i32;
+            let line_rect = Rect::at(rect.left(), y).of_size(rect.width(), scale.ceil() as u32);
+            let align = (self.align.0, AlignVertical::Top);
+            let drawn = draw_aligned_text(
+                canvas,
+                self.color,
+                anchor(line_rect, align),
+                Scale::uniform(scale),
+                font,
+                line,
+                align,
+            );
+            occupied = Some(match occupied {
+                Some(x) => union(x, drawn),
+                None => drawn,
+            });
+        }
+        Ok(occupied.unwrap_or(Rect::at(rect.left(), rect.top()).of_size(1, 1)))
+    }
+}
+
+fn union(a: Rect, b: Rect) -> Rect {
+    let left = a.left().min(b.left());
+    let top = a.top().min(b.top());
+    let right = a.right().max(b.right());
+    let bottom = a.bottom().max(b.bottom());
+    Rect::at(left, top).of_size((right - left + 1) as u32, (bottom - top + 1) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn font() -> FontStack {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/Tuffy.ttf");
+        let font = Font::try_from_vec(std::fs::read(path).unwrap()).unwrap();
+        FontStack::new("main", vec![font])
+    }
+
+    const SCALE: Scale = Scale { x: 20.0, y: 20.0 };
+
+    #[test]
+    fn wrap_mixed() {
+        let font = font();
+        let text = "Hello 世界，你好 world";
+        let width = text_width(&font, SCALE, "Hello 世界，");
+        let lines = wrap_text(&font, SCALE, text, width);
+        assert_eq!(lines[0], "Hello 世界，");
+        assert!(lines[1].starts_with("你好"), "{:?}", lines);
+        // the comma may not start a line, it takes 界 along
+        let width = text_width(&font, SCALE, "Hello 世界");
+        let lines = wrap_text(&font, SCALE, text, width);
+        assert_eq!(lines[0], "Hello 世");
+        assert!(lines[1].starts_with("界，"), "{:?}", lines);
+        assert!(lines.iter().all(|x| text_width(&font, SCALE, x) <= width));
+    }
+
+    #[test]
+    fn long_word() {
+        let font = font();
+        let word = "Supercalifragilistic";
+        let width = text_width(&font, SCALE, "Super…");
+        assert_eq!(ellipsize(&font, SCALE, word, width), "Super…");
+        let lines = wrap_text(&font, SCALE, word, width);
+        assert_eq!(lines.concat(), word);
+        assert!(lines.iter().all(|x| text_width(&font, SCALE, x) <= width));
+    }
+
+    #[test]
+    fn fit_max_lines() {
+        let font = font();
+        let style = ParagraphStyle {
+            font: "main",
+            color: Luma([0]),
+            align: (AlignHorizontal::Left, AlignVertical::Top),
+            max_scale: Some(40.0),
+            min_scale: 12.0,
+            max_lines: 2,
+            line_spacing: 1.2,
+        };
+        let rect = Rect::at(0, 0).of_size(120, 200);
+        let text = "the quick brown fox jumps over the lazy dog ".repeat(4);
+        let (lines, scale) = style.fit(&font, rect, &text);
+        assert_eq!(scale, 12.0);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].ends_with('…'), "{:?}", lines);
+        assert!(lines
+            .iter()
+            .all(|x| text_width(&font, Scale::uniform(scale), x) <= rect.width()));
+    }
+}
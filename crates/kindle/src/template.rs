@@ -0,0 +1,197 @@
+// Styles drawn from `*.svg` files under `kindle.templates`: every `{{key}}`
+// is replaced by the value of the binding, e.g. `{{now.day}}` or the short
+// `{{day}}`, `{{weather.0.text}}`, then the SVG is rasterized with resvg.
+// Text uses the configured font files, `font-family="main"` selects a stack
+// by its name and characters missing from a font fall back to the others.
+use super::layout::resolve;
+use super::shared::*;
+use anyhow::{anyhow, Result};
+use image::{GrayImage, Luma};
+use log::{debug, error, info, warn};
+use once_cell::sync::{Lazy, OnceCell};
+use resvg::{tiny_skia, usvg};
+use std::collections::HashMap;
+use std::sync::Arc;
+use usvg::fontdb::{Database, Source, ID};
+
+static TEMPLATES: OnceCell<HashMap<String, String>> = OnceCell::new();
+
+/// the configured fonts, and the first face of each stack by its name
+static FONT_DB: Lazy<(Arc<Database>, HashMap<String, ID>)> = Lazy::new(|| {
+    let mut db = Database::new();
+    let mut faces = HashMap::new();
+    let mut loaded: HashMap<&String, ID> = HashMap::new();
+    for (name, paths) in font_files().into_iter().flatten() {
+        for path in paths {
+            if !loaded.contains_key(path) {
+                match std::fs::read(path) {
+                    Ok(data) => {
+                        let ids = db.load_font_source(Source::Binary(Arc::new(data)));
+                        if let Some(id) = ids.first() {
+                            loaded.insert(path, *id);
+                        }
+                    }
+                    Err(e) => error!("failed to load {} for templates: {}", path, e),
+                }
+            }
+        }
+        if let Some(id) = paths.first().and_then(|x| loaded.get(x)) {
+            faces.insert(name.clone(), *id);
+        }
+    }
+    if let Some(family) = faces
+        .get("main")
+        .and_then(|x| db.face(*x))
+        .and_then(|x| x.families.first())
+        .map(|x| x.0.clone())
+    {
+        db.set_serif_family(family.clone());
+        db.set_sans_serif_family(family);
+    }
+    (Arc::new(db), faces)
+});
+
+pub fn load_templates(path: Option<String>) {
+    TEMPLATES.get_or_init(|| {
+        let mut map = HashMap::new();
+        let Some(path) = path else {
+            return map;
+        };
+        let entries = match std::fs::read_dir(&path) {
+            Ok(entries) => entries,
+            Err(err) => {
+                warn!("cannot read templates from {}: {}", path, err);
+                return map;
+            }
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|x| x != "svg") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|x| x.to_str()) else {
+                continue;
+            };
+            let raw = match std::fs::read_to_string(&path) {
+                Ok(raw) => raw,
+                Err(err) => {
+                    error!("failed to load template {:?}: {}", path, err);
+                    continue;
+                }
+            };
+            match usvg::roxmltree::Document::parse(&raw) {
+                Ok(_) => {
+                    info!("loading template {}: {:?}", name, path);
+                    map.insert(name.to_string(), raw);
+                }
+                Err(err) => error!("failed to load template {:?}: {}", path, err),
+            }
+        }
+        map
+    });
+}
+
+pub fn get_template(name: &str) -> Option<&'static str> {
+    TEMPLATES
+        .get()
+        .and_then(|x| x.get(name))
+        .map(|x| x.as_str())
+}
+
+pub fn list_templates() -> Vec<&'static str> {
+    TEMPLATES
+        .get()
+        .map(|x| x.keys().map(|x| x.as_str()).collect())
+        .unwrap_or_default()
+}
+
+/// a layout binding, or a short one: `day` for `now.day`, `weather.0.text`
+/// for `weather.3d[0].text`
+fn lookup(key: &str, context: &Context) -> Option<String> {
+    let segments: Vec<&str> = key.split('.').collect();
+    let path = match segments.as_slice() {
+        ["battery"] => key.to_string(),
+        [field] => format!("now.{}", field),
+        ["weather", index, field] if index.parse::<usize>().is_ok() => {
+            format!("weather.3d.{}.{}", index, field)
+        }
+        _ => key.to_string(),
+    };
+    resolve(&path, context)
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// replace every `{{key}}` of the template, unknown ones by nothing
+fn fill(template: &str, context: &Context) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        filled.push_str(&rest[..start]);
+        let key = rest[start + 2..start + end].trim();
+        match lookup(key, context) {
+            Some(value) => filled.push_str(&escape(&value)),
+            None => debug!("template binding {} has no value", key),
+        }
+        rest = &rest[start + end + 2..];
+    }
+    filled.push_str(rest);
+    filled
+}
+
+fn options() -> usvg::Options<'static> {
+    let (fontdb, faces) = &*FONT_DB;
+    let default_selector = usvg::FontResolver::default_font_selector();
+    usvg::Options {
+        fontdb: fontdb.clone(),
+        font_family: "sans-serif".to_string(),
+        font_resolver: usvg::FontResolver {
+            select_font: Box::new(move |font, fontdb| {
+                font.families()
+                    .iter()
+                    .find_map(|family| match family {
+                        usvg::FontFamily::Named(name) => faces.get(name).copied(),
+                        _ => None,
+                    })
+                    .or_else(|| default_selector(font, fontdb))
+            }),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+/// the filled template fitted into the canvas on white
+pub fn generate(img: &mut GrayImage, template: &str, context: &Context) -> Result<()> {
+    let svg = fill(template, context);
+    let tree = usvg::Tree::from_str(&svg, &options())?;
+    let size = tree.size();
+    let scale = (img.width() as f32 / size.width()).min(img.height() as f32 / size.height());
+    let dx = (img.width() as f32 - size.width() * scale) / 2.0;
+    let dy = (img.height() as f32 - size.height() * scale) / 2.0;
+    let mut pixmap =
+        tiny_skia::Pixmap::new(img.width(), img.height()).ok_or(anyhow!("empty canvas"))?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale).post_translate(dx, dy),
+        &mut pixmap.as_mut(),
+    );
+    // premultiplied colors over white
+    for (pixel, out) in pixmap.pixels().iter().zip(img.pixels_mut()) {
+        let white = 255 - pixel.alpha() as u32;
+        let luma =
+            (pixel.red() as u32 * 299 + pixel.green() as u32 * 587 + pixel.blue() as u32 * 114)
+                / 1000;
+        *out = Luma([(luma + white).min(255) as u8]);
+    }
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use super::super::shared::*;
+use anyhow::Result;
+use image::GrayImage;
+use imageproc::rect::Rect;
+
+/// events of the day which have not ended yet, all-day events first
+pub fn upcoming(context: &Context) -> Vec<&agenda::Event> {
+    let mut events: Vec<&agenda::Event> = context
+        .agenda
+        .iter()
+        .filter(|x| context.now.is_none_or(|now| x.end > now))
+        .collect();
+    events.sort_by_key(|x| (!x.all_day, x.start));
+    events
+}
+
+/// 全天 生日, 09:00 游泳课
+pub fn agenda_text(event: &agenda::Event, context: &Context) -> String {
+    if event.all_day {
+        format!("{} {}", context.locale.all_day(), event.summary)
+    } else {
+        let start = context.local(event.start);
+        format!("{} {}", start.format("%H:%M"), event.summary)
+    }
+}
+
+/// one line per event, `count` lines share the rect
+pub struct AgendaWidget {
+    pub style: TextStyle,
+    pub count: usize,
+}
+
+impl Widget for AgendaWidget {
+    fn render(&self, canvas: &mut GrayImage, rect: Rect, context: &Context) -> Result<()> {
+        let height = rect.height() / self.count.max(1) as u32;
+        // too small for a line of text
+        if height * 3 / 4 == 0 {
+            return Ok(());
+        }
+        for (index, event) in upcoming(context).into_iter().take(self.count).enumerate() {
+            let line = Rect::at(rect.left(), rect.top() + (height * index as u32) as i32)
+                .of_size(rect.width(), height * 3 / 4);
+            // long titles shrink a little, then end with an ellipsis
+            let style = ParagraphStyle {
+                font: self.style.font,
+                color: self.style.color,
+                align: self.style.align,
+                max_scale: None,
+                min_scale: line.height() as f32 * 0.8,
+                max_lines: 1,
+                line_spacing: 1.0,
+            };
+            style.draw(canvas, line, &agenda_text(event, context))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use super::super::history::BatteryHistory;
+use super::super::locale::Locale;
+use super::super::shared::*;
+use anyhow::Result;
+use image::{GrayImage, Luma};
+use imageproc::{drawing, rect::Rect};
+
+pub struct BatteryWidget {
+    pub style: TextStyle,
+    pub text: fn(usize, Locale) -> String,
+}
+
+impl Widget for BatteryWidget {
+    fn render(&self, canvas: &mut GrayImage, rect: Rect, context: &Context) -> Result<()> {
+        if let Some(battery) = context.battery {
+            self.style
+                .draw(canvas, rect, &(self.text)(battery, context.locale))?;
+        }
+        Ok(())
+    }
+}
+
+/// battery level over the last `hours` hours, 0% at the bottom of the rect
+pub struct BatterySparklineWidget {
+    pub color: Luma<u8>,
+    pub hours: i64,
+}
+
+impl Widget for BatterySparklineWidget {
+    fn render(&self, canvas: &mut GrayImage, rect: Rect, context: &Context) -> Result<()> {
+        let Some(now) = context.now else {
+            return Ok(());
+        };
+        let end = now.timestamp();
+        let start = end - self.hours * 3600;
+        let points: Vec<(f32, f32)> = context
+            .battery_history
+            .readings
+            .iter()
+            .filter(|x| x.time >= start && x.time <= end)
+            .map(|x| {
+                let progress = (x.time - start) as f32 / (end - start) as f32;
+                (
+                    rect.left() as f32 + progress * (rect.width() - 1) as f32,
+                    rect.bottom() as f32
+                        - x.level.min(100) as f32 / 100.0 * (rect.height() - 1) as f32,
+                )
+            })
+            .collect();
+        for pair in points.windows(2) {
+            for offset in [0.0, 1.0] {
+                let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+                drawing::draw_line_segment_mut(
+                    canvas,
+                    (x0, y0 - offset),
+                    (x1, y1 - offset),
+                    self.color,
+                );
+            }
+        }
+        if let Some(&(x, y)) = points.last() {
+            let radius = (rect.height() / 12).max(2) as i32;
+            drawing::draw_filled_circle_mut(canvas, (x as i32, y as i32), radius, self.color);
+        }
+        Ok(())
+    }
+}
+
+/// e.g. time to empty, or charging
+pub struct BatteryEstimateWidget {
+    pub style: TextStyle,
+    pub text: fn(&BatteryHistory, Locale) -> Option<String>,
+}
+
+impl Widget for BatteryEstimateWidget {
+    fn render(&self, canvas: &mut GrayImage, rect: Rect, context: &Context) -> Result<()> {
+        if let Some(text) = (self.text)(&context.battery_history, context.locale) {
+            self.style.draw(canvas, rect, &text)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+use super::super::locale::{stacked_day, Locale};
+use super::super::shared::*;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, FixedOffset};
+use image::{GrayImage, Luma};
+use imageproc::{drawing, rect::Rect};
+use rusttype::Scale;
+
+pub enum DayStyle {
+    Arabic,
+    /// written out in the numerals of the locale
+    Words,
+    /// two chinese numerals stacked vertically, each centered on its ink
+    ChineseStacked,
+}
+
+pub struct DayWidget {
+    pub style: TextStyle,
+    pub day: DayStyle,
+}
+
+impl Widget for DayWidget {
+    fn render(&self, canvas: &mut GrayImage, rect: Rect, context: &Context) -> Result<()> {
+        let now = context.now.ok_or(anyhow!("time not provided"))?;
+        match self.day {
+            DayStyle::Arabic => {
+                self.style
+                    .draw(canvas, rect, &now.format("%d").to_string())?;
+            }
+            DayStyle::Words => {
+                let day = context.locale.numerals().day(now.day());
+                self.style.draw(canvas, rect, &day)?;
+            }
+            DayStyle::ChineseStacked => {
+                let font = get_font(self.style.font)
+                    .ok_or(anyhow!("{} font not found", self.style.font))?;
+                let (ch0, ch1) = stacked_day(now.day());
+                let x = rect.left() + rect.width() as i32 / 2;
+                let quarter = rect.height() as i32 / 4;
+                let scale = Scale::uniform(rect.width() as f32);
+                draw_centered_text(
+                    canvas,
+                    self.style.color,
+                    (x, rect.top() + quarter),
+                    scale,
+                    font,
+                    ch0,
+                );
+                draw_centered_text(
+                    canvas,
+                    self.style.color,
+                    (x, rect.top() + quarter * 3),
+                    scale,
+                    font,
+                    ch1,
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct WeekdayWidget {
+    pub style: TextStyle,
+    /// the short name of the locale
+    pub short: bool,
+    /// fill the rect and center the name on its ink
+    pub background: Option<Luma<u8>>,
+}
+
+impl Widget for WeekdayWidget {
+    fn render(&self, canvas: &mut GrayImage, rect: Rect, context: &Context) -> Result<()> {
+        let now = context.now.ok_or(anyhow!("time not provided"))?;
+        let name = match self.short {
+            true => context.locale.weekday_short(now.weekday()),
+            false => context.locale.weekday_long(now.weekday()),
+        };
+        match self.background {
+            Some(background) => {
+                let font = get_font(self.style.font)
+                    .ok_or(anyhow!("{} font not found", self.style.font))?;
+                drawing::draw_filled_rect_mut(canvas, rect, background);
+                // sized for one character, longer names shrink to the width
+                let mut scale = rect.height() as f32;
+                if let Some(ink) = font.bounds(Scale::uniform(scale), name) {
+                    let fit = rect.width() as f32 * 0.9 / ink.width() as f32;
+                    scale *= fit.min(1.0);
+                }
+                draw_centered_text(
+                    canvas,
+                    self.style.color,
+                    anchor(rect, (AlignHorizontal::Center, AlignVertical::Center)),
+                    Scale::uniform(scale),
+                    font,
+                    name,
+                );
+            }
+            None => {
+                self.style.draw(canvas, rect, name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// the year and month of today, such as 2024年10月
+pub struct MonthTitleWidget {
+    pub style: TextStyle,
+}
+
+impl Widget for MonthTitleWidget {
+    fn render(&self, canvas: &mut GrayImage, rect: Rect, context: &Context) -> Result<()> {
+        let now = context.now.ok_or(anyhow!("time not provided"))?;
+        self.style
+            .draw(canvas, rect, &context.locale.month_title(now.date_naive()))?;
+        Ok(())
+    }
+}
+
+pub struct UpdateTimeWidget {
+    pub style: TextStyle,
+    pub text: fn(DateTime<FixedOffset>, Locale) -> String,
+}
+
+impl Widget for UpdateTimeWidget {
+    fn render(&self, canvas: &mut GrayImage, rect: Rect, context: &Context) -> Result<()> {
+        let now = context.now.ok_or(anyhow!("time not provided"))?;
+        self.style
+            .draw(canvas, rect, &(self.text)(now, context.locale))?;
+        Ok(())
+    }
+}
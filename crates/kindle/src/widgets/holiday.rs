@@ -0,0 +1,34 @@
+use super::super::locale::Locale;
+use super::super::shared::*;
+use anyhow::Result;
+use holiday::{Day, Kind};
+use image::GrayImage;
+use imageproc::rect::Rect;
+
+/// 休 · 国庆节 on statutory holidays, 班 · 国庆节调休 on make-up workdays
+pub fn holiday_text(day: &Day, locale: Locale) -> Option<String> {
+    let name = day.name.as_deref().unwrap_or_default();
+    match day.kind {
+        Kind::Holiday => Some(locale.holiday(name)),
+        Kind::Adjusted => Some(locale.adjusted(name)),
+        Kind::Workday | Kind::Weekend => None,
+    }
+}
+
+/// nothing on ordinary workdays and weekends
+pub struct HolidayWidget {
+    pub style: TextStyle,
+}
+
+impl Widget for HolidayWidget {
+    fn render(&self, canvas: &mut GrayImage, rect: Rect, context: &Context) -> Result<()> {
+        let text = context
+            .holiday
+            .as_ref()
+            .and_then(|x| holiday_text(x, context.locale));
+        if let Some(text) = text {
+            self.style.draw(canvas, rect, &text)?;
+        }
+        Ok(())
+    }
+}
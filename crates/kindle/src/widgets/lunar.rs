@@ -0,0 +1,44 @@
+use super::super::lunar;
+use super::super::shared::*;
+use anyhow::{anyhow, Result};
+use image::GrayImage;
+use imageproc::rect::Rect;
+
+pub struct LunarWidget {
+    pub style: TextStyle,
+    /// prefix with the sexagenary year, e.g. 甲辰龙年
+    pub year: bool,
+}
+
+impl Widget for LunarWidget {
+    fn render(&self, canvas: &mut GrayImage, rect: Rect, context: &Context) -> Result<()> {
+        let now = context.now.ok_or(anyhow!("time not provided"))?;
+        let date = lunar::lunar_date(now.date_naive());
+        let mut text = format!("{}{}", date.month_name(), date.day_name());
+        if self.year {
+            text = format!("{}{}年 {}", date.year_name(), date.zodiac(), text);
+        }
+        self.style.draw(canvas, rect, &text)?;
+        Ok(())
+    }
+}
+
+/// traditional festival and solar term of the day, nothing on other days
+pub struct FestivalWidget {
+    pub style: TextStyle,
+}
+
+impl Widget for FestivalWidget {
+    fn render(&self, canvas: &mut GrayImage, rect: Rect, context: &Context) -> Result<()> {
+        let now = context.now.ok_or(anyhow!("time not provided"))?;
+        let date = now.date_naive();
+        let names: Vec<&str> = [lunar::festival(date), lunar::solar_term(date)]
+            .into_iter()
+            .flatten()
+            .collect();
+        if !names.is_empty() {
+            self.style.draw(canvas, rect, &names.join(" "))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+mod agenda;
+mod battery;
+mod date;
+mod holiday;
+mod lunar;
+mod month;
+mod qr;
+mod quote;
+mod weather;
+
+use super::shared::*;
+use anyhow::Result;
+use image::{GrayImage, Luma};
+use imageproc::{drawing, rect::Rect};
+
+pub use agenda::{agenda_text, upcoming, AgendaWidget};
+pub use battery::{BatteryEstimateWidget, BatterySparklineWidget, BatteryWidget};
+pub use date::{DayStyle, DayWidget, MonthTitleWidget, UpdateTimeWidget, WeekdayWidget};
+pub use holiday::{holiday_text, HolidayWidget};
+pub use lunar::{FestivalWidget, LunarWidget};
+pub use month::MonthWidget;
+pub use qr::QrWidget;
+pub use quote::QuoteWidget;
+pub use weather::{Forecast24hWidget, Forecast3dWidget, HourlyChartWidget};
+
+pub struct Fill {
+    pub color: Luma<u8>,
+}
+
+impl Widget for Fill {
+    fn render(&self, canvas: &mut GrayImage, rect: Rect, _context: &Context) -> Result<()> {
+        drawing::draw_filled_rect_mut(canvas, rect, self.color);
+        Ok(())
+    }
+}
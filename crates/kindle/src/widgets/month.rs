@@ -0,0 +1,116 @@
+use super::super::shared::*;
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use image::{GrayImage, Luma};
+use imageproc::{drawing, rect::Rect};
+
+/// days from `start` to `day` going forward, 0..7
+fn days_since(day: Weekday, start: Weekday) -> u32 {
+    (day.num_days_from_monday() + 7 - start.num_days_from_monday()) % 7
+}
+
+fn days_in_month(first: NaiveDate) -> i64 {
+    let next = match first.month() {
+        12 => NaiveDate::from_ymd_opt(first.year() + 1, 1, 1),
+        month => NaiveDate::from_ymd_opt(first.year(), month + 1, 1),
+    };
+    (next.unwrap() - first).num_days()
+}
+
+/// the current month as a grid, today is inverted, weekends and holidays
+/// are shaded, make-up workdays are not
+pub struct MonthWidget {
+    pub style: TextStyle,
+    pub week_start: Weekday,
+    /// add a column of ISO week numbers in front
+    pub week_numbers: bool,
+    pub shade: Luma<u8>,
+}
+
+impl MonthWidget {
+    fn cell(&self, rect: Rect, width: u32, height: u32, column: u32, row: u32) -> Rect {
+        Rect::at(
+            rect.left() + (column * width) as i32,
+            rect.top() + (row * height) as i32,
+        )
+        .of_size(width.max(1), height.max(1))
+    }
+
+    fn label(&self, canvas: &mut GrayImage, cell: Rect, color: Luma<u8>, text: &str) -> Result<()> {
+        let height = (cell.height() / 2).max(1);
+        let inner = Rect::at(
+            cell.left(),
+            cell.top() + (cell.height() - height) as i32 / 2,
+        )
+        .of_size(cell.width(), height);
+        let style = TextStyle {
+            color,
+            align: (AlignHorizontal::Center, AlignVertical::Center),
+            ..self.style
+        };
+        style.draw(canvas, inner, text)?;
+        Ok(())
+    }
+}
+
+impl Widget for MonthWidget {
+    fn render(&self, canvas: &mut GrayImage, rect: Rect, context: &Context) -> Result<()> {
+        let today = context
+            .now
+            .ok_or(anyhow!("time not provided"))?
+            .date_naive();
+        let first = today.with_day(1).unwrap();
+        let offset = days_since(first.weekday(), self.week_start);
+        let days = days_in_month(first);
+        let rows = (offset + days as u32).div_ceil(7);
+        let columns = if self.week_numbers { 8 } else { 7 };
+        let skip = columns - 7;
+        let width = rect.width() / columns;
+        let height = rect.height() / (rows + 1);
+
+        let mut weekday = self.week_start;
+        for column in 0..7 {
+            let name = context.locale.weekday_short(weekday);
+            let cell = self.cell(rect, width, height, column + skip, 0);
+            self.label(canvas, cell, self.style.color, name)?;
+            weekday = weekday.succ();
+        }
+
+        for index in 0..days {
+            let date = first + Duration::days(index);
+            let position = offset + index as u32;
+            let (column, row) = (position % 7, position / 7 + 1);
+            let cell = self.cell(rect, width, height, column + skip, row);
+            let padded = Rect::at(cell.left() + 2, cell.top() + 2).of_size(
+                cell.width().saturating_sub(4).max(1),
+                cell.height().saturating_sub(4).max(1),
+            );
+            let color = if date == today {
+                drawing::draw_filled_rect_mut(canvas, padded, self.style.color);
+                Luma([255 - self.style.color.0[0]])
+            } else {
+                if !holiday::query(date).is_workday() {
+                    drawing::draw_filled_rect_mut(canvas, padded, self.shade);
+                }
+                self.style.color
+            };
+            self.label(canvas, cell, color, &date.day().to_string())?;
+        }
+
+        if self.week_numbers {
+            for row in 0..rows {
+                // every row holds exactly one monday
+                let monday = first - Duration::days(offset as i64)
+                    + Duration::days((row * 7 + days_since(Weekday::Mon, self.week_start)) as i64);
+                let cell = self.cell(rect, width, height, 0, row + 1);
+                self.label(
+                    canvas,
+                    cell,
+                    Luma([128]),
+                    &monday.iso_week().week().to_string(),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use super::super::qr;
+use super::super::shared::*;
+use anyhow::Result;
+use image::GrayImage;
+use imageproc::rect::Rect;
+use log::warn;
+
+/// a qr code of `payload`, or of `kindle.qr` if absent, nothing without one
+/// or when it does not fit
+pub struct QrWidget {
+    pub payload: Option<&'static str>,
+}
+
+impl Widget for QrWidget {
+    fn render(&self, canvas: &mut GrayImage, rect: Rect, _context: &Context) -> Result<()> {
+        let payload = match self.payload {
+            Some(payload) => Some(payload.to_string()),
+            None => qr::qr_payload(),
+        };
+        if let Some(payload) = payload {
+            // the inbox link grows with its id, which must not fail the screen
+            if let Err(e) = qr::draw_qr(canvas, rect, &payload) {
+                warn!("qr code left out: {:?}", e);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    #[test]
+    fn too_small() {
+        let mut canvas = GrayImage::from_pixel(100, 100, Luma([255]));
+        let widget = QrWidget {
+            payload: Some("http://192.168.1.2:8000/inbox/api/get/0123456789abcdef"),
+        };
+        let rect = Rect::at(10, 10).of_size(20, 20);
+        widget
+            .render(&mut canvas, rect, &Context::default())
+            .unwrap();
+        assert!(canvas.pixels().all(|x| x.0[0] == 255));
+    }
+
+    #[test]
+    fn fits() {
+        let mut canvas = GrayImage::from_pixel(100, 100, Luma([255]));
+        let widget = QrWidget {
+            payload: Some("http://192.168.1.2:8000/inbox/api/get/0123456789abcdef"),
+        };
+        let rect = Rect::at(0, 0).of_size(100, 100);
+        widget
+            .render(&mut canvas, rect, &Context::default())
+            .unwrap();
+        assert!(canvas.pixels().any(|x| x.0[0] == 0));
+    }
+}
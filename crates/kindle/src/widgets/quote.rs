@@ -0,0 +1,30 @@
+use super::super::quote::quote_of;
+use super::super::shared::*;
+use anyhow::Result;
+use image::GrayImage;
+use imageproc::rect::Rect;
+
+/// the quote of the day wrapped into the rect, its author on the last line
+pub struct QuoteWidget {
+    pub style: ParagraphStyle,
+    pub author: TextStyle,
+}
+
+impl Widget for QuoteWidget {
+    fn render(&self, canvas: &mut GrayImage, rect: Rect, context: &Context) -> Result<()> {
+        let Some(quote) = context.now.and_then(|x| quote_of(x.date_naive())) else {
+            return Ok(());
+        };
+        let Some(author) = quote.author else {
+            self.style.draw(canvas, rect, &quote.text)?;
+            return Ok(());
+        };
+        let line = (self.style.min_scale.round() as u32).clamp(1, rect.height() / 2);
+        let text = Rect::at(rect.left(), rect.top()).of_size(rect.width(), rect.height() - line);
+        let drawn = self.style.draw(canvas, text, &quote.text)?;
+        let top = (drawn.bottom() + 1 + line as i32 / 2).min(rect.bottom() + 1 - line as i32);
+        let below = Rect::at(rect.left(), top).of_size(rect.width(), line);
+        self.author.draw(canvas, below, &format!("— {}", author))?;
+        Ok(())
+    }
+}
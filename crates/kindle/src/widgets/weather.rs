@@ -0,0 +1,156 @@
+use super::super::icon::{draw_icon, has_icon};
+use super::super::shared::*;
+use anyhow::Result;
+use image::{GrayImage, Luma};
+use imageproc::{drawing, rect::Rect};
+
+pub struct Forecast24hWidget {
+    pub style: TextStyle,
+}
+
+impl Widget for Forecast24hWidget {
+    fn render(&self, canvas: &mut GrayImage, rect: Rect, context: &Context) -> Result<()> {
+        if let Some(forecast) = &context.forecast_24h {
+            let text = format!("{}~{}", forecast.min_temp, forecast.max_temp);
+            self.style.draw(canvas, rect, &text)?;
+        }
+        Ok(())
+    }
+}
+
+/// one column per day, weather icon (or text without icons) above and
+/// temperature range below
+pub struct Forecast3dWidget {
+    pub style: TextStyle,
+}
+
+impl Widget for Forecast3dWidget {
+    fn render(&self, canvas: &mut GrayImage, rect: Rect, context: &Context) -> Result<()> {
+        let Some(forecast) = &context.forecast_3d else {
+            return Ok(());
+        };
+        if forecast.len() != 3 {
+            return Ok(());
+        }
+        let width = rect.width() / 3;
+        let text_height = rect.height() * 3 / 5;
+        let temp_height = rect.height() - text_height;
+        for (i, day) in forecast.iter().enumerate() {
+            let x = rect.left() + (width * i as u32) as i32;
+            let area = Rect::at(x, rect.top()).of_size(width, text_height);
+            if has_icon(&day.icon) {
+                draw_icon(canvas, area, &day.icon, self.style.color)?;
+            } else {
+                // descriptions like "Light Rain" get two lines
+                let style = ParagraphStyle {
+                    font: self.style.font,
+                    color: self.style.color,
+                    align: (AlignHorizontal::Center, AlignVertical::Bottom),
+                    max_scale: None,
+                    min_scale: area.height() as f32 / 4.0,
+                    max_lines: 2,
+                    line_spacing: 1.1,
+                };
+                style.draw(canvas, area, &day.text)?;
+            }
+            let style = TextStyle {
+                align: (AlignHorizontal::Center, AlignVertical::Top),
+                ..self.style
+            };
+            style.draw(
+                canvas,
+                Rect::at(x, rect.top() + text_height as i32).of_size(width, temp_height),
+                &format!("{}-{}", day.temp_min, day.temp_max),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// hourly temperature as a line over precipitation probability bars, with
+/// the extremes labelled and a time label every `every` hours
+pub struct HourlyChartWidget {
+    pub style: TextStyle,
+    pub line: Luma<u8>,
+    pub bar: Luma<u8>,
+    pub every: usize,
+}
+
+impl Widget for HourlyChartWidget {
+    fn render(&self, canvas: &mut GrayImage, rect: Rect, context: &Context) -> Result<()> {
+        let Some(forecast) = &context.forecast_24h else {
+            return Ok(());
+        };
+        let hours = &forecast.hourly;
+        if hours.len() < 2 {
+            return Ok(());
+        }
+        let label = (rect.height() / 7).max(1);
+        let plot = Rect::at(rect.left(), rect.top() + label as i32)
+            .of_size(rect.width(), rect.height().saturating_sub(label * 3).max(1));
+        let step = plot.width() as f32 / hours.len() as f32;
+        let x = |i: usize| plot.left() as f32 + step * (i as f32 + 0.5);
+        let span = (forecast.max_temp - forecast.min_temp).max(1) as f32;
+        let y = |temp: i32| {
+            plot.bottom() as f32 - (temp - forecast.min_temp) as f32 / span * plot.height() as f32
+        };
+
+        for (i, hour) in hours.iter().enumerate() {
+            let Some(pop) = hour.pop.filter(|x| *x > 0) else {
+                continue;
+            };
+            let height = (plot.height() as f32 * pop.min(100) as f32 / 100.0).max(1.0) as u32;
+            let width = (step * 0.6).max(1.0) as u32;
+            let bar = Rect::at(
+                x(i) as i32 - width as i32 / 2,
+                plot.bottom() - height as i32 + 1,
+            )
+            .of_size(width, height);
+            drawing::draw_filled_rect_mut(canvas, bar, self.bar);
+        }
+
+        let thickness = (rect.height() / 80).max(1) as i32;
+        for (i, pair) in hours.windows(2).enumerate() {
+            for offset in -thickness / 2..=thickness / 2 {
+                let offset = offset as f32;
+                drawing::draw_line_segment_mut(
+                    canvas,
+                    (x(i), y(pair[0].temp) + offset),
+                    (x(i + 1), y(pair[1].temp) + offset),
+                    self.line,
+                );
+            }
+        }
+
+        let label_at = |i: usize, top: i32, align: AlignVertical| {
+            let width = label * 4;
+            (
+                Rect::at(x(i) as i32 - width as i32 / 2, top).of_size(width, label),
+                TextStyle {
+                    align: (AlignHorizontal::Center, align),
+                    ..self.style
+                },
+            )
+        };
+        if let Some(i) = hours.iter().position(|x| x.temp == forecast.max_temp) {
+            let top = y(forecast.max_temp) as i32 - label as i32 - thickness;
+            let (rect, style) = label_at(i, top, AlignVertical::Bottom);
+            style.draw(canvas, rect, &format!("{}°", forecast.max_temp))?;
+        }
+        if let Some(i) = hours.iter().position(|x| x.temp == forecast.min_temp) {
+            let top = y(forecast.min_temp) as i32 + thickness + 2;
+            let (rect, style) = label_at(i, top, AlignVertical::Top);
+            style.draw(canvas, rect, &format!("{}°", forecast.min_temp))?;
+        }
+        for (i, hour) in hours.iter().enumerate().step_by(self.every.max(1)) {
+            let top = rect.bottom() - label as i32 + 1;
+            let (rect, style) = label_at(i, top, AlignVertical::Bottom);
+            style.draw(
+                canvas,
+                rect,
+                &context.local(hour.fx_time).format("%H:%M").to_string(),
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+// Renders every builtin style and a fixture SVG template at a fixed time
+// with fixture forecasts and compares it to the golden image in
+// tests/snapshots. Fonts are the public domain Tuffy in tests/fixtures, so
+// CJK text shows as missing glyphs, which still catches moved or resized
+// widgets.
+//
+// UPDATE_SNAPSHOTS=1 cargo test -p kindle --test snapshots
+// rewrites the golden images, a missing one fails the test.
+use chrono::{FixedOffset, TimeZone};
+use image::GrayImage;
+use kindle::{Context, Locale};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// a pixel differs when its gray level moves further than this
+const THRESHOLD: u8 = 32;
+/// share of differing pixels tolerated, for rasterizer rounding
+const TOLERANCE: f64 = 0.002;
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+async fn context() -> Context {
+    let font = fixture("Tuffy.ttf").to_str().unwrap().to_string();
+    kindle::load_fonts(HashMap::from(
+        ["main", "weather", "status"].map(|x| (x.to_string(), vec![font.clone()])),
+    ));
+    kindle::set_quotes(Some(fixture("quotes.tsv").to_str().unwrap().to_string()));
+    kindle::load_templates(Some(fixture("templates").to_str().unwrap().to_string()));
+    let weather = std::fs::read_to_string(fixture("weather.json")).unwrap();
+    qweather::load_fixture(&serde_json::from_str(&weather).unwrap())
+        .await
+        .unwrap();
+    let now = FixedOffset::east_opt(8 * 3600)
+        .unwrap()
+        .with_ymd_and_hms(2024, 10, 1, 8, 30, 0)
+        .unwrap();
+    let mut context = Context {
+        battery: Some(77),
+        now: Some(now),
+        ..Default::default()
+    };
+    context.fetch().await;
+    context
+}
+
+fn compare(name: &str, actual: &GrayImage) {
+    let golden = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(format!("{}.png", name));
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        actual.save(&golden).unwrap();
+        return;
+    }
+    assert!(
+        golden.exists(),
+        "{} has no snapshot, write it with UPDATE_SNAPSHOTS=1",
+        name
+    );
+    let expected = image::open(&golden).unwrap().to_luma8();
+    assert_eq!(
+        expected.dimensions(),
+        actual.dimensions(),
+        "{} changed its size",
+        name
+    );
+    let differing = expected
+        .pixels()
+        .zip(actual.pixels())
+        .filter(|(a, b)| a.0[0].abs_diff(b.0[0]) > THRESHOLD)
+        .count();
+    let ratio = differing as f64 / (actual.width() * actual.height()) as f64;
+    if ratio > TOLERANCE {
+        let output = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.png", name));
+        actual.save(&output).unwrap();
+        panic!(
+            "{} differs from its snapshot in {:.2}% of pixels, see {}",
+            name,
+            ratio * 100.0,
+            output.display()
+        );
+    }
+}
+
+async fn snapshot(style: &str) {
+    let context = context().await;
+    let img = kindle::factory(Some(style), &context).await.unwrap();
+    compare(style, &img);
+}
+
+/// latin labels are drawn by the fixture font, unlike the chinese ones
+async fn snapshot_en(style: &str) {
+    let context = Context {
+        locale: Locale::En,
+        ..context().await
+    };
+    let img = kindle::factory(Some(style), &context).await.unwrap();
+    compare(&format!("{}_en", style), &img);
+}
+
+#[tokio::test]
+async fn alpha() {
+    snapshot("alpha").await;
+}
+
+#[tokio::test]
+async fn bravo() {
+    snapshot("bravo").await;
+}
+
+#[tokio::test]
+async fn charlie() {
+    snapshot("charlie").await;
+}
+
+#[tokio::test]
+async fn delta() {
+    snapshot("delta").await;
+}
+
+#[tokio::test]
+async fn echo() {
+    snapshot("echo").await;
+}
+
+#[tokio::test]
+async fn night() {
+    snapshot("night").await;
+}
+
+/// tests/fixtures/templates/card.svg
+#[tokio::test]
+async fn card() {
+    snapshot("card").await;
+}
+
+#[tokio::test]
+async fn alpha_en() {
+    snapshot_en("alpha").await;
+}
+
+#[tokio::test]
+async fn charlie_en() {
+    snapshot_en("charlie").await;
+}
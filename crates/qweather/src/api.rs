@@ -3,6 +3,7 @@ use chrono::{DateTime, FixedOffset, Local, NaiveTime};
 use log::info;
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::sync::Mutex;
 
 #[derive(Debug)]
@@ -26,11 +27,18 @@ const API_URL_NOW: &str = "https://devapi.qweather.com/v7/weather/now";
 
 static DATA_24H: Lazy<Mutex<Vec<HourlyForecast>>> = Lazy::new(|| Mutex::new(Vec::new()));
 static DATA_3D: Lazy<Mutex<Vec<DailyForecast>>> = Lazy::new(|| Mutex::new(Vec::new()));
+static DATA_VERSION: AtomicU64 = AtomicU64::new(0);
+
+/// bumped whenever the cached forecasts are updated
+pub fn data_version() -> u64 {
+    DATA_VERSION.load(Ordering::Relaxed)
+}
 
 pub struct Forcast24H {
     pub min_temp: i32,
     pub max_temp: i32,
     pub texts: Vec<String>,
+    pub hourly: Vec<HourlyForecast>,
 }
 
 pub async fn get_24h_forcast() -> Result<Forcast24H> {
@@ -50,6 +58,7 @@ pub async fn get_24h_forcast() -> Result<Forcast24H> {
         min_temp,
         max_temp,
         texts,
+        hourly: data.clone(),
     });
 }
 
@@ -68,6 +77,8 @@ struct HourlyForecastRaw {
     temp: String,
     humidity: String,
     text: String,
+    #[serde(default)]
+    pop: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -76,6 +87,8 @@ pub struct HourlyForecast {
     pub temp: i32,
     pub humidity: i32,
     pub text: String,
+    /// probability of precipitation in percent, not available everywhere
+    pub pop: Option<i32>,
 }
 
 impl std::convert::TryFrom<&HourlyForecastRaw> for HourlyForecast {
@@ -85,12 +98,14 @@ impl std::convert::TryFrom<&HourlyForecastRaw> for HourlyForecast {
             .or(Err(anyhow!("failed to parse fxTime")))?;
         let fx_time = DateTime::<Local>::try_from(fx_time)?;
         let temp: i32 = value.temp.parse()?;
-        let humidity: i32 = value.temp.parse()?;
+        let humidity: i32 = value.humidity.parse()?;
+        let pop = value.pop.as_ref().and_then(|x| x.parse().ok());
         return Ok(HourlyForecast {
             fx_time: fx_time,
             temp: temp,
             humidity: humidity,
             text: value.text.clone(),
+            pop,
         });
     }
 }
@@ -186,9 +201,12 @@ pub async fn update_24h() -> Result<()> {
     if code != "200" {
         return Err(anyhow!("query failed"));
     }
-    let mut raw = DATA_24H.lock().await;
-
     let hourly = json.get("hourly").ok_or(anyhow!("hourly not found"))?;
+    store_24h(hourly).await
+}
+
+async fn store_24h(hourly: &serde_json::Value) -> Result<()> {
+    let mut raw = DATA_24H.lock().await;
     let hour_forcast_raw: Vec<HourlyForecastRaw> = serde_json::from_value(hourly.clone())?;
     *raw = hour_forcast_raw
         .iter()
@@ -196,6 +214,7 @@ pub async fn update_24h() -> Result<()> {
         .filter(|x| x.is_ok())
         .map(|x| x.unwrap())
         .collect::<Vec<HourlyForecast>>();
+    DATA_VERSION.fetch_add(1, Ordering::Relaxed);
     return Ok(());
 }
 
@@ -229,9 +248,12 @@ pub async fn update_3d() -> Result<()> {
     if code != "200" {
         return Err(anyhow!("query failed"));
     }
-    let mut raw = DATA_3D.lock().await;
-
     let daily = json.get("daily").ok_or(anyhow!("daily not found"))?;
+    store_3d(daily).await
+}
+
+async fn store_3d(daily: &serde_json::Value) -> Result<()> {
+    let mut raw = DATA_3D.lock().await;
     let daily_forecast_raw: Vec<DailyForecastRaw> = serde_json::from_value(daily.to_owned())?;
     *raw = daily_forecast_raw
         .iter()
@@ -239,9 +261,22 @@ pub async fn update_3d() -> Result<()> {
         .filter(|x| x.is_ok())
         .map(|x| x.unwrap())
         .collect::<Vec<DailyForecast>>();
+    DATA_VERSION.fetch_add(1, Ordering::Relaxed);
     return Ok(());
 }
 
+/// fill the forecasts from a saved `{"hourly": [...], "daily": [...]}` in the
+/// shape of the 24h and 3d responses, to render without the API
+pub async fn load_fixture(json: &serde_json::Value) -> Result<()> {
+    if let Some(hourly) = json.get("hourly") {
+        store_24h(hourly).await?;
+    }
+    if let Some(daily) = json.get("daily") {
+        store_3d(daily).await?;
+    }
+    Ok(())
+}
+
 #[allow(non_snake_case)]
 #[derive(Serialize, Deserialize, Debug)]
 struct CurrentWeatherRaw {
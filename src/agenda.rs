@@ -0,0 +1,41 @@
+use chrono::{Local, NaiveDate};
+use rocket::figment::Figment;
+use rocket::response::status::NotFound;
+use rocket::serde::json::Json;
+use rocket::{Build, Rocket};
+
+pub fn init(config: &Figment) {
+    let calendars = config
+        .find_value("agenda.calendars")
+        .ok()
+        .and_then(|x| x.deserialize::<Vec<String>>().ok())
+        .unwrap_or_default();
+    agenda::init(
+        calendars
+            .iter()
+            .map(|x| {
+                let path = std::path::Path::new(utils::get_data_path()).join(x);
+                path.to_str().unwrap().to_string()
+            })
+            .collect(),
+    );
+}
+
+pub fn build(base: &'static str, build: Rocket<Build>) -> Rocket<Build> {
+    build.mount(base, routes![main])
+}
+
+#[get("/?<date>&<days>")]
+fn main(date: Option<String>, days: Option<i64>) -> Result<Json<Vec<agenda::Event>>, NotFound<()>> {
+    let date = match date {
+        Some(raw) => match NaiveDate::parse_from_str(&raw, "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(e) => {
+                error!("{:?}", e);
+                return Err(NotFound(()));
+            }
+        },
+        None => Local::now().date_naive(),
+    };
+    Ok(Json(agenda::agenda(date, days.unwrap_or(1).clamp(1, 366))))
+}
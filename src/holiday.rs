@@ -0,0 +1,34 @@
+use chrono::{Local, NaiveDate};
+use rocket::figment::Figment;
+use rocket::response::status::NotFound;
+use rocket::serde::json::Json;
+use rocket::{Build, Rocket};
+
+pub fn init(config: &Figment) {
+    let path = config
+        .find_value("holiday.path")
+        .ok()
+        .and_then(|x| x.into_string())
+        .unwrap_or("holidays".to_string());
+    let path = std::path::Path::new(utils::get_data_path()).join(path);
+    holiday::init(path.to_str().unwrap());
+}
+
+pub fn build(base: &'static str, build: Rocket<Build>) -> Rocket<Build> {
+    build.mount(base, routes![main])
+}
+
+#[get("/?<date>")]
+fn main(date: Option<String>) -> Result<Json<holiday::Day>, NotFound<()>> {
+    let date = match date {
+        Some(raw) => match NaiveDate::parse_from_str(&raw, "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(e) => {
+                error!("{:?}", e);
+                return Err(NotFound(()));
+            }
+        },
+        None => Local::now().date_naive(),
+    };
+    Ok(Json(holiday::query(date)))
+}
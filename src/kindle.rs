@@ -1,99 +1,600 @@
-use chrono::{self, Local, NaiveDate};
-use kindle::Context;
+use anyhow::anyhow;
+use chrono::{self, Local, NaiveDate, NaiveDateTime, Timelike, Weekday};
+use kindle::{
+    BatteryHistory, Context, Device, Dither, Format, Locale, Photos, Profile, Qr, Quote, Refresh,
+    RenderKey, Slot, DEFAULT_DEVICE,
+};
 use rocket::figment::Figment;
-use rocket::response::status::NotFound;
-use rocket::{http::ContentType, Build, Rocket};
+use rocket::http::Status;
+use rocket::request::{self, FromRequest};
+use rocket::response::{self, status::BadRequest, status::NotFound, Responder};
+use rocket::serde::json::{self, Json, Value};
+use rocket::serde::Serialize;
+use rocket::{http::ContentType, Build, FromForm, Request, Response, Rocket};
 use std::collections::HashMap;
 use std::io::Cursor;
+use std::sync::Arc;
 use std::vec;
 
 pub fn build(base: &'static str, build: Rocket<Build>, config: &Figment) -> Rocket<Build> {
+    init(config);
+    build.mount(
+        base,
+        routes![main, battery, devices, refresh, qr, add_quote],
+    )
+}
+
+/// styles, fonts, profiles and everything else screens are drawn with
+pub fn init(config: &Figment) {
     kindle::set_default_style(
         config
             .find_value("kindle.style")
             .ok()
-            .and_then(|x| x.to_i128().and_then(|x| Some(x as usize))),
+            .and_then(|x| match x.to_i128() {
+                Some(x) => Some(x.to_string()),
+                None => x.into_string(),
+            })
+            .filter(|x| !x.is_empty()),
     );
+    if let Some(week_start) = config
+        .find_value("kindle.week_start")
+        .ok()
+        .and_then(|x| x.into_string())
+    {
+        match week_start.parse::<Weekday>() {
+            Ok(week_start) => kindle::set_week_start(week_start),
+            Err(_) => error!("invalid kindle.week_start = {}", week_start),
+        }
+    }
+    if let Some(locale) = config
+        .find_value("kindle.locale")
+        .ok()
+        .and_then(|x| x.into_string())
+        .filter(|x| !x.is_empty())
+    {
+        match locale.parse::<Locale>() {
+            Ok(locale) => kindle::set_default_locale(locale),
+            Err(e) => error!("invalid kindle.locale: {}", e),
+        }
+    }
+    if let Ok(schedule) = config.find_value("kindle.schedule") {
+        match schedule.deserialize::<Vec<Slot>>() {
+            Ok(schedule) => kindle::set_schedule(schedule),
+            Err(e) => error!("invalid kindle.schedule: {:?}", e),
+        }
+    }
+    if let Ok(refresh) = config.find_value("kindle.refresh") {
+        match refresh.deserialize::<Refresh>() {
+            Ok(refresh) => kindle::set_refresh(refresh),
+            Err(e) => error!("invalid kindle.refresh: {:?}", e),
+        }
+    }
+    if let Ok(photos) = config.find_value("kindle.photos") {
+        match photos.deserialize::<Photos>() {
+            Ok(photos) => kindle::set_photos(photos),
+            Err(e) => error!("invalid kindle.photos: {:?}", e),
+        }
+    }
+    if let Ok(qr) = config.find_value("kindle.qr") {
+        match qr.deserialize::<Qr>() {
+            Ok(qr) => kindle::set_qr(qr),
+            Err(e) => error!("invalid kindle.qr: {:?}", e),
+        }
+    }
     let mut font_map = HashMap::new();
     let list = config.find_value("kindle.fonts");
     if let Ok(list) = list {
         if let Some(list) = list.as_dict() {
             for item in list {
                 let name = item.0;
-                if let Some(path) = item.1.as_str() {
-                    let path = std::path::Path::new(utils::get_data_path()).join(path);
-                    font_map.insert(name.clone(), path.to_str().unwrap().to_string());
+                // a single file or a stack of fallbacks
+                let paths: Vec<&str> = match item.1.as_array() {
+                    Some(paths) => paths.iter().filter_map(|x| x.as_str()).collect(),
+                    None => item.1.as_str().into_iter().collect(),
+                };
+                let paths: Vec<String> = paths
+                    .into_iter()
+                    .filter(|x| !x.is_empty())
+                    .map(|x| {
+                        let path = std::path::Path::new(utils::get_data_path()).join(x);
+                        path.to_str().unwrap().to_string()
+                    })
+                    .collect();
+                if !paths.is_empty() {
+                    font_map.insert(name.clone(), paths);
                 }
             }
         }
     }
     kindle::load_fonts(font_map);
-    build.mount(base, routes![main])
+    let mut profiles = HashMap::new();
+    if let Some(values) = config
+        .find_value("kindle.profiles")
+        .ok()
+        .and_then(|x| x.into_dict())
+    {
+        for (name, value) in values {
+            match value.deserialize::<Profile>() {
+                Ok(profile) => {
+                    profiles.insert(name, profile);
+                }
+                Err(e) => error!("invalid kindle.profiles.{}: {:?}", name, e),
+            }
+        }
+    }
+    kindle::load_profiles(
+        profiles,
+        config
+            .find_value("kindle.profile")
+            .ok()
+            .and_then(|x| x.into_string())
+            .filter(|x| !x.is_empty()),
+    );
+    kindle::load_devices(
+        config
+            .find_value("kindle.devices")
+            .ok()
+            .and_then(|x| x.deserialize::<HashMap<String, Device>>().ok())
+            .unwrap_or_default(),
+    );
+    kindle::load_icons(
+        config
+            .find_value("kindle.icons")
+            .ok()
+            .and_then(|x| x.into_string())
+            .map(|x| {
+                let path = std::path::Path::new(utils::get_data_path()).join(x);
+                path.to_str().unwrap().to_string()
+            }),
+    );
+    kindle::set_quotes(
+        config
+            .find_value("kindle.quotes")
+            .ok()
+            .and_then(|x| x.into_string())
+            .filter(|x| !x.is_empty())
+            .map(|x| {
+                let path = std::path::Path::new(utils::get_data_path()).join(x);
+                path.to_str().unwrap().to_string()
+            }),
+    );
+    kindle::load_layouts(
+        config
+            .find_value("kindle.layouts")
+            .ok()
+            .and_then(|x| x.into_string())
+            .map(|x| {
+                let path = std::path::Path::new(utils::get_data_path()).join(x);
+                path.to_str().unwrap().to_string()
+            }),
+    );
+    kindle::load_templates(
+        config
+            .find_value("kindle.templates")
+            .ok()
+            .and_then(|x| x.into_string())
+            .map(|x| {
+                let path = std::path::Path::new(utils::get_data_path()).join(x);
+                path.to_str().unwrap().to_string()
+            }),
+    );
+}
+
+#[derive(clap::Args)]
+pub struct RenderArgs {
+    /// builtin style, layout or template name, by the device and the schedule if absent
+    #[arg(long)]
+    style: Option<String>,
+    /// id under kindle.devices, for its profile, style and timezone
+    #[arg(long, default_value = DEFAULT_DEVICE)]
+    device: String,
+    /// YYYY-MM-DD or YYYY-MM-DDTHH:MM on the clock of the device, now if absent
+    #[arg(long)]
+    date: Option<String>,
+    #[arg(long)]
+    battery: Option<usize>,
+    /// zh-CN, en or ja, by the device if absent
+    #[arg(long)]
+    locale: Option<String>,
+    /// saved forecasts as {"hourly": [...], "daily": [...]}
+    #[arg(long)]
+    weather: Option<String>,
+    /// png file to write
+    #[arg(short, long, default_value = "kindle.png")]
+    output: String,
+}
+
+/// `?locale=` or `--locale`, then the device, then `kindle.locale`
+fn locale_of(requested: Option<&str>, device: &Device) -> anyhow::Result<Locale> {
+    match requested.or(device.locale.as_deref()) {
+        Some(locale) => locale.parse(),
+        None => Ok(kindle::default_locale()),
+    }
+}
+
+/// draw a screen without launching the server, only configured device
+/// settings are used so the result depends on the arguments alone
+pub async fn render(args: RenderArgs) -> anyhow::Result<()> {
+    let device = kindle::configured_device(&args.device);
+    let now = match args.date.as_deref() {
+        Some(raw) => {
+            let naive = NaiveDateTime::parse_from_str(raw, "%Y-%m-%dT%H:%M")
+                .or_else(|_| NaiveDate::parse_from_str(raw, "%Y-%m-%d").map(|x| x.into()))?;
+            device
+                .localize(naive)
+                .ok_or(anyhow!("{} does not exist in {}", raw, device.name()))?
+        }
+        None => device
+            .now()
+            .with_second(0)
+            .unwrap()
+            .with_nanosecond(0)
+            .unwrap(),
+    };
+    if let Some(path) = args.weather {
+        let json: Value = json::from_str(&std::fs::read_to_string(path)?)?;
+        qweather::load_fixture(&json).await?;
+    }
+    let profile = kindle::get_profile(device.profile.as_deref())?;
+    let locale = locale_of(args.locale.as_deref(), &device)?;
+    let style = args.style.or(device.style);
+    let mut context = Context {
+        device: Some(device.id.clone()),
+        battery: args.battery,
+        now: Some(now),
+        profile,
+        locale,
+        ..Default::default()
+    };
+    context.fetch().await;
+    let mut img = kindle::factory(style.as_deref(), &context).await?;
+    if let Some(levels) = context.profile.levels {
+        let dither = context.profile.dither.unwrap_or(Dither::FloydSteinberg);
+        kindle::quantize(&mut img, levels, dither);
+    }
+    context.profile.rotate(img).save(&args.output)?;
+    info!("rendered {}", args.output);
+    Ok(())
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct BatteryReport {
+    level: Option<usize>,
+    charging: bool,
+    /// percent per hour
+    discharge_rate: Option<f64>,
+    /// seconds
+    time_to_empty: Option<i64>,
+    #[serde(flatten)]
+    history: BatteryHistory,
+}
+
+#[get("/battery?<device>")]
+fn battery(device: Option<String>) -> Json<BatteryReport> {
+    let device = kindle::get_device(device.as_deref().unwrap_or(DEFAULT_DEVICE));
+    let history = BatteryHistory::load(&utils::database::Db::new(), &device.history_key());
+    Json(BatteryReport {
+        level: history.level(),
+        charging: history.charging(),
+        discharge_rate: history.discharge_rate(),
+        time_to_empty: history.time_to_empty().map(|x| x.num_seconds()),
+        history,
+    })
+}
+
+#[get("/devices")]
+fn devices() -> Json<Vec<Device>> {
+    Json(kindle::list_devices())
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct NextRefresh {
+    /// RFC 3339 in the timezone of the device
+    at: String,
+    seconds: i64,
+}
+
+impl NextRefresh {
+    fn of(device: &Device) -> NextRefresh {
+        let now = device.now();
+        let at = kindle::next_refresh(now, device.battery);
+        NextRefresh {
+            at: at.to_rfc3339(),
+            seconds: (at - now).num_seconds(),
+        }
+    }
 }
 
-async fn save_battery(battery: usize) -> Result<(), anyhow::Error> {
+/// for clients which would rather not parse headers of the image
+#[get("/refresh?<device>")]
+fn refresh(device: Option<String>) -> Json<NextRefresh> {
+    let device = kindle::get_device(device.as_deref().unwrap_or(DEFAULT_DEVICE));
+    Json(NextRefresh::of(&device))
+}
+
+/// `text` as a qr code, `kindle.qr` if absent, `module` pixels per module
+#[get("/qr?<text>&<module>")]
+fn qr(text: Option<String>, module: Option<u32>) -> Result<(ContentType, Vec<u8>), NotFound<()>> {
+    let Some(text) = text.or_else(kindle::qr_payload) else {
+        return Err(NotFound(()));
+    };
+    kindle::qr_image(&text, module.unwrap_or(8).clamp(1, 64))
+        .and_then(|img| kindle::encode(&img, Format::Png, false))
+        .map(|data| (ContentType::PNG, data))
+        .map_err(|e| {
+            error!("{:?}", e);
+            NotFound(())
+        })
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct QuoteCount {
+    count: usize,
+}
+
+/// append a quote to `kindle.quotes`
+#[post("/quotes", format = "json", data = "<quote>")]
+fn add_quote(quote: Json<Quote>) -> Result<Json<QuoteCount>, BadRequest<String>> {
+    let quote = quote.into_inner();
+    if quote.text.trim().is_empty() {
+        return Err(BadRequest("empty quote".to_string()));
+    }
+    match kindle::add_quote(quote) {
+        Ok(count) => Ok(Json(QuoteCount { count })),
+        Err(e) => {
+            error!("{:?}", e);
+            Err(BadRequest(e.to_string()))
+        }
+    }
+}
+
+async fn save_battery(device: String, battery: usize) -> Result<(), anyhow::Error> {
     use influxdb2::models::DataPoint;
     tsdb::write(vec![DataPoint::builder("device")
-        .tag("name", "kindle")
+        .tag("name", device)
         .field("power", battery as f64)
         .build()?])
     .await?;
     Ok(())
 }
 
-#[get("/?<battery>&<style>&<now>")]
-async fn main(
+pub struct IfNoneMatch(Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IfNoneMatch {
+    type Error = ();
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        request::Outcome::Success(IfNoneMatch(
+            request
+                .headers()
+                .get_one("If-None-Match")
+                .map(|x| x.to_string()),
+        ))
+    }
+}
+
+/// `refresh` is the number of seconds until the client should fetch again
+pub enum Screen {
+    Image {
+        content_type: ContentType,
+        data: Arc<Vec<u8>>,
+        etag: String,
+        refresh: i64,
+    },
+    NotModified {
+        etag: String,
+        refresh: i64,
+    },
+}
+
+impl<'r> Responder<'r, 'static> for Screen {
+    fn respond_to(self, _request: &'r Request<'_>) -> response::Result<'static> {
+        match self {
+            Screen::Image {
+                content_type,
+                data,
+                etag,
+                refresh,
+            } => Response::build()
+                .header(content_type)
+                .raw_header("ETag", etag)
+                .raw_header("X-Next-Refresh", refresh.to_string())
+                .sized_body(data.len(), Cursor::new(data.to_vec()))
+                .ok(),
+            Screen::NotModified { etag, refresh } => Response::build()
+                .status(Status::NotModified)
+                .raw_header("ETag", etag)
+                .raw_header("X-Next-Refresh", refresh.to_string())
+                .ok(),
+        }
+    }
+}
+
+/// query of a screen, every field falls back to the device and the config
+#[derive(FromForm)]
+struct ScreenQuery {
+    device: Option<String>,
     battery: Option<usize>,
-    style: Option<usize>,
+    style: Option<String>,
+    /// YYYY-MM-DD
     now: Option<String>,
-) -> Result<(ContentType, Vec<u8>), NotFound<()>> {
-    info!("{:?}", now);
-    let now = match now {
-        Some(raw) => {
-            let date = NaiveDate::parse_from_str(&raw, "%Y-%m-%d");
-            if let Ok(date) = date {
-                let naive_datetime = date.and_hms_opt(0, 0, 0).unwrap();
-                naive_datetime.and_local_timezone(Local).unwrap()
-            } else {
-                Local::now()
-            }
+    profile: Option<String>,
+    locale: Option<String>,
+    levels: Option<u32>,
+    dither: Option<String>,
+    format: Option<String>,
+    invert: Option<bool>,
+}
+
+#[get("/?<query..>")]
+async fn main(query: ScreenQuery, if_none_match: IfNoneMatch) -> Result<Screen, NotFound<()>> {
+    let ScreenQuery {
+        device,
+        battery,
+        style,
+        now,
+        profile,
+        locale,
+        levels,
+        dither,
+        format,
+        invert,
+    } = query;
+    let mut device = kindle::get_device(device.as_deref().unwrap_or(DEFAULT_DEVICE));
+    let style = style.or(device.style.clone());
+    let profile_name = profile.or(device.profile.clone());
+    let profile = match kindle::get_profile(profile_name.as_deref()) {
+        Ok(profile) => profile,
+        Err(e) => {
+            error!("{:?}", e);
+            return Err(NotFound(()));
+        }
+    };
+    let locale = match locale_of(locale.as_deref(), &device) {
+        Ok(locale) => locale,
+        Err(e) => {
+            error!("{:?}", e);
+            return Err(NotFound(()));
         }
-        None => Local::now(),
     };
-    let mut battery = battery;
+    info!("{:?}", now);
+    let now = now
+        .and_then(|raw| NaiveDate::parse_from_str(&raw, "%Y-%m-%d").ok())
+        .and_then(|date| device.localize(date.and_hms_opt(0, 0, 0)?))
+        .unwrap_or_else(|| device.now());
+    // screens are cached per minute, so never show anything finer
+    let now = now.with_second(0).unwrap().with_nanosecond(0).unwrap();
+    let db = utils::database::Db::new();
+    let mut history = BatteryHistory::load(&db, &device.history_key());
     if let Some(battery) = battery {
-        let db = utils::database::Db::new();
-        tokio::spawn(save_battery(battery));
-        let _ = db.set("kindle/battery", &battery);
-        if battery < 20 {
-            bark::send(bark::Message {
-                body: &format!("kindle's battery is low: {}%", battery),
-                ..Default::default()
-            })
-            .await;
+        tokio::spawn(save_battery(device.id.clone(), battery));
+        device.battery = Some(battery);
+        if history.record(Local::now().timestamp(), battery) {
+            info!("{} started charging at {}%", device.name(), battery);
         }
-    } else {
-        let db = utils::database::Db::new();
-        battery = db.get("kindle/battery").unwrap_or(None);
+        if let Err(e) = history.save(&db, &device.history_key()) {
+            error!("failed to save battery history: {:?}", e);
+        }
+    }
+    // once per discharge, until it is charged above the threshold
+    let low_battery = battery.is_some_and(|x| x < 20);
+    let notify = low_battery && !device.low_battery;
+    if battery.is_some() {
+        device.low_battery = low_battery;
+    }
+    let battery = device.battery;
+    device.last_seen = Some(Local::now().timestamp());
+    if let Err(e) = kindle::save_device(&device) {
+        error!("failed to save device {}: {:?}", device.id, e);
+    }
+    if notify {
+        bark::send(bark::Message {
+            body: &format!(
+                "{}'s battery is low: {}%",
+                device.name(),
+                battery.unwrap_or(0)
+            ),
+            ..Default::default()
+        })
+        .await;
     }
-    let context = Context {
-        battery: battery,
+    let dither = match dither.map(|x| x.parse::<Dither>()).transpose() {
+        Ok(dither) => dither.or(profile.dither),
+        Err(e) => {
+            error!("{:?}", e);
+            return Err(NotFound(()));
+        }
+    };
+    let format = match format.map(|x| x.parse::<Format>()).transpose() {
+        Ok(format) => format.or(profile.format).unwrap_or(Format::Png),
+        Err(e) => {
+            error!("{:?}", e);
+            return Err(NotFound(()));
+        }
+    };
+    let levels = match (levels.or(profile.levels), format.levels()) {
+        (Some(levels), Some(limit)) => Some(levels.min(limit)),
+        (levels, limit) => levels.or(limit),
+    };
+    let invert = invert.unwrap_or(false);
+    let content_type = match format {
+        Format::Png => ContentType::PNG,
+        Format::Bmp => ContentType::BMP,
+        Format::Raw1 | Format::Raw4 => ContentType::Binary,
+    };
+
+    // read before the forecasts, an update in between renders again later
+    let weather = qweather::data_version();
+    let mut context = Context {
+        device: Some(device.id.clone()),
+        battery,
+        battery_history: history,
         now: Some(now),
+        profile,
+        locale,
+        ..Default::default()
+    };
+    context.fetch().await;
+    let quote = kindle::quote_of(now.date_naive());
+    let key = RenderKey {
+        device: &device.id,
+        style: style.as_deref(),
+        profile: profile_name.as_deref(),
+        locale,
+        minute: now.timestamp() / 60,
+        battery,
+        readings: &context.battery_history.readings,
+        weather,
+        holiday: context.holiday.as_ref(),
+        agenda: &context.agenda,
+        quote: quote.as_ref(),
+        levels,
+        dither,
+        format,
+        invert,
     };
+    let etag = format!("\"{:016x}\"", key.tag());
+    let refresh = NextRefresh::of(&device).seconds;
+    if if_none_match.0.as_ref() == Some(&etag) {
+        info!("not modified: {}", etag);
+        return Ok(Screen::NotModified { etag, refresh });
+    }
+    if let Some(data) = kindle::get_render(&key) {
+        info!("cache hit: {}", etag);
+        return Ok(Screen::Image {
+            content_type,
+            data,
+            etag,
+            refresh,
+        });
+    }
+
     info!("style={:?}", style);
     info!("now={:?}", context.now);
     info!("battery={:?}", context.battery);
 
-    match kindle::factory(style, &context).await {
-        Ok(img) => {
-            let mut buffer: Vec<u8> = Vec::new();
-            img.write_to(&mut Cursor::new(&mut buffer), image::ImageOutputFormat::Png)
-                .expect("failed to encoded image");
-            return Ok((ContentType::PNG, buffer));
+    match kindle::factory(style.as_deref(), &context).await {
+        Ok(mut img) => {
+            if let Some(levels) = levels {
+                kindle::quantize(&mut img, levels, dither.unwrap_or(Dither::FloydSteinberg));
+            }
+            let img = context.profile.rotate(img);
+            let data =
+                Arc::new(kindle::encode(&img, format, invert).expect("failed to encoded image"));
+            kindle::put_render(&key, data.clone());
+            Ok(Screen::Image {
+                content_type,
+                data,
+                etag,
+                refresh,
+            })
         }
         Err(e) => {
             error!("{:?}", e);
-            return Err(NotFound(()));
+            Err(NotFound(()))
         }
     }
 }
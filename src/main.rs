@@ -9,7 +9,9 @@ use rocket::figment::{
 #[macro_use]
 extern crate rocket;
 
+mod agenda;
 mod fairings;
+mod holiday;
 mod kindle;
 mod let_server_run;
 mod logger;
@@ -31,6 +33,8 @@ enum Commands {
     Go,
     /// print version and exit
     Version,
+    /// draw a kindle screen to a png without launching the server
+    Render(kindle::RenderArgs),
 }
 
 fn is_enabled(config: &Figment, name: &str, default: bool) -> bool {
@@ -98,6 +102,15 @@ async fn go(config: &Figment) -> Result<(), rocket::Error> {
         error!("last launch not found");
     }
 
+    holiday::init(&config);
+    if is_enabled(&config, "holiday", false) {
+        wtf = holiday::build("/holiday", wtf);
+    }
+    agenda::init(&config);
+    if is_enabled(&config, "agenda", false) {
+        wtf = agenda::build("/agenda", wtf);
+    }
+
     if is_enabled(&config, "tsdb", false) {
         wtf = tsdb::build(wtf, &config).await;
     }
@@ -156,6 +169,23 @@ async fn main() -> Result<(), rocket::Error> {
             println!("{}", VERSION);
             Ok(())
         }
+        Some(Commands::Render(args)) => {
+            if let Some(data) = config
+                .find_value("data_path")
+                .ok()
+                .and_then(|x| x.into_string())
+            {
+                utils::init_data_path(&data);
+            }
+            holiday::init(&config);
+            agenda::init(&config);
+            kindle::init(&config);
+            if let Err(e) = kindle::render(args).await {
+                error!("{:?}", e);
+                std::process::exit(1);
+            }
+            Ok(())
+        }
         Some(Commands::Go) | None => go(&config).await,
     }
 }